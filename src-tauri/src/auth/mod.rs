@@ -0,0 +1,3 @@
+pub mod password;
+pub mod permissions;
+pub mod session;
@@ -0,0 +1,54 @@
+use crate::error::BmsError;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+
+/// Failed logins allowed before the account is temporarily locked.
+pub const MAX_FAILED_ATTEMPTS: i32 = 5;
+/// SQLite datetime modifier for how long a locked account stays locked.
+pub const LOCKOUT_DURATION: &str = "+15 minutes";
+
+/// Hash of a throwaway password, made with the default parameters. Checking
+/// against it for an unknown username makes that login take as long as a
+/// wrong password for a real one.
+const DUMMY_HASH: &str = "$argon2id$v=19$m=19456,t=2,p=1$RYQB+s9wMKk/x/ebvk/JKg$A8kBfutcMqpcPOEK8ZRMmlqCmSwuiAIAM4AF36C0Ftc";
+
+pub enum PasswordCheck {
+    Valid,
+    /// Matched a plaintext password stored before hashing was introduced;
+    /// the caller should replace it with a fresh hash.
+    ValidLegacy,
+    Invalid,
+}
+
+/// Hashes with Argon2id and a random per-user salt, returning a PHC string
+/// that carries the algorithm, parameters and salt alongside the hash.
+pub fn hash_password(password: &str) -> Result<String, BmsError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| BmsError::invalid("password", e.to_string()))
+}
+
+pub fn verify_password(stored: &str, candidate: &str) -> PasswordCheck {
+    match PasswordHash::new(stored) {
+        Ok(hash) => {
+            if Argon2::default()
+                .verify_password(candidate.as_bytes(), &hash)
+                .is_ok()
+            {
+                PasswordCheck::Valid
+            } else {
+                PasswordCheck::Invalid
+            }
+        }
+        Err(_) if stored == candidate => PasswordCheck::ValidLegacy,
+        Err(_) => PasswordCheck::Invalid,
+    }
+}
+
+/// Does the work of a password check that can never succeed.
+pub fn verify_dummy(candidate: &str) {
+    let _ = verify_password(DUMMY_HASH, candidate);
+}
@@ -0,0 +1,25 @@
+//! Which roles may call which groups of commands. Every command in
+//! `commands/*` passes one of these to `SessionState::require`.
+
+use crate::models::user::Role;
+
+/// Any logged-in user, viewers included. Used for read-only commands.
+pub const ANY_ROLE: &[Role] = &[
+    Role::Admin,
+    Role::Secretary,
+    Role::Treasurer,
+    Role::Kagawad,
+    Role::Viewer,
+];
+
+/// Residents, households, blotters, events and the logbook.
+pub const RECORD_EDITORS: &[Role] = &[Role::Admin, Role::Secretary, Role::Kagawad];
+
+/// Incomes and expenses.
+pub const TREASURY: &[Role] = &[Role::Treasurer];
+
+/// Issuing, editing and deleting certificates.
+pub const CERTIFICATE_ISSUERS: &[Role] = &[Role::Secretary];
+
+/// Settings, officials and user accounts.
+pub const ADMINISTRATORS: &[Role] = &[Role::Admin];
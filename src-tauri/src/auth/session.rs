@@ -0,0 +1,83 @@
+use crate::error::BmsError;
+use crate::models::user::{Role, SessionUser};
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Sessions with no activity for this long are dropped and must log in again.
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+struct Session {
+    user: SessionUser,
+    last_active: Instant,
+}
+
+/// Logged-in sessions keyed by opaque token, registered with
+/// `tauri::Builder::manage`. The frontend receives the token from
+/// `login_command` and passes it back with every guarded command.
+#[derive(Default)]
+pub struct SessionState {
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl SessionState {
+    /// Starts a session for `user` and returns its token.
+    pub fn begin(&self, user: SessionUser) -> Result<String, BmsError> {
+        let token = new_token();
+        let mut sessions = self.lock()?;
+        sessions.retain(|_, s| s.last_active.elapsed() < IDLE_TIMEOUT);
+        sessions.insert(
+            token.clone(),
+            Session {
+                user,
+                last_active: Instant::now(),
+            },
+        );
+        Ok(token)
+    }
+
+    pub fn end(&self, token: &str) -> Result<(), BmsError> {
+        self.lock()?.remove(token);
+        Ok(())
+    }
+
+    /// Returns the user behind `token`, refreshing the idle timer.
+    pub fn current(&self, token: &str) -> Result<SessionUser, BmsError> {
+        let mut sessions = self.lock()?;
+
+        match sessions.get_mut(token) {
+            None => Err(BmsError::unauthorized("You must be logged in to do this")),
+            Some(session) if session.last_active.elapsed() < IDLE_TIMEOUT => {
+                session.last_active = Instant::now();
+                Ok(session.user.clone())
+            }
+            Some(_) => {
+                sessions.remove(token);
+                Err(BmsError::unauthorized("Your session has expired. Please log in again"))
+            }
+        }
+    }
+
+    /// Returns the user behind `token` if their role is one of `allowed`.
+    pub fn require(&self, token: &str, allowed: &[Role]) -> Result<SessionUser, BmsError> {
+        let user = self.current(token)?;
+        if allowed.contains(&user.role) {
+            Ok(user)
+        } else {
+            Err(BmsError::unauthorized("You do not have permission to do this"))
+        }
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, HashMap<String, Session>>, BmsError> {
+        self.sessions
+            .lock()
+            .map_err(|_| BmsError::unauthorized("Session is unavailable"))
+    }
+}
+
+fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,82 @@
+use crate::auth::permissions::ADMINISTRATORS;
+use crate::auth::session::SessionState;
+use crate::database::connection::DbPool;
+use crate::database::query::{Filters, MAX_PAGE_SIZE};
+use crate::error::BmsError;
+use crate::models::audit::{AuditEntry, AuditLogFilter};
+use crate::models::money::RoundingAdjustment;
+use rusqlite::params_from_iter;
+use tauri::State;
+
+const DEFAULT_LIMIT: i64 = 500;
+
+#[tauri::command]
+pub fn fetch_audit_log_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, filter: Option<AuditLogFilter>) -> Result<Vec<AuditEntry>, BmsError> {
+    session.require(&token, ADMINISTRATORS)?;
+    let conn = pool.get()?;
+    let filter = filter.unwrap_or_default();
+
+    let mut filters = Filters::new();
+    filters
+        .opt("table_name = ?", filter.table_name)
+        .opt("row_id = ?", filter.row_id)
+        .opt("user_id = ?", filter.user_id)
+        .opt("operation = ?", filter.operation)
+        .opt("date(occurred_at) >= date(?)", filter.date_from)
+        .opt("date(occurred_at) <= date(?)", filter.date_to);
+    let limit = filter.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_PAGE_SIZE);
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT id, user_id, username, occurred_at, table_name, row_id, operation, changes
+             FROM audit_log {} ORDER BY id DESC LIMIT {}",
+            filters.where_sql(),
+            limit
+        ))?;
+
+    let entries_iter = stmt
+        .query_map(params_from_iter(filters.values()), |row| {
+            let changes: String = row.get(7)?;
+            Ok(AuditEntry {
+                id: row.get(0)?,
+                user_id: row.get(1)?,
+                username: row.get(2)?,
+                occurred_at: row.get(3)?,
+                table_name: row.get(4)?,
+                row_id: row.get(5)?,
+                operation: row.get(6)?,
+                changes: serde_json::from_str(&changes).unwrap_or_default(),
+            })
+        })?;
+
+    let mut entries = Vec::new();
+    for entry in entries_iter {
+        entries.push(entry?);
+    }
+
+    Ok(entries)
+}
+
+/// Amounts that had to be rounded when they were converted to centavos.
+#[tauri::command]
+pub fn fetch_money_rounding_report_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String) -> Result<Vec<RoundingAdjustment>, BmsError> {
+    session.require(&token, ADMINISTRATORS)?;
+    let conn = pool.get()?;
+
+    let mut stmt = conn.prepare_cached(
+        "SELECT table_name, row_id, original, amount, converted_at FROM money_rounding_report ORDER BY table_name, row_id",
+    )?;
+    let adjustments = stmt
+        .query_map([], |row| {
+            Ok(RoundingAdjustment {
+                table_name: row.get(0)?,
+                row_id: row.get(1)?,
+                original: row.get(2)?,
+                amount: row.get(3)?,
+                converted_at: row.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(adjustments)
+}
@@ -1,49 +1,224 @@
-use rusqlite::params;
-use crate::database::connection::establish_connection;
-use crate::models::blotter::Blotter;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use crate::auth::permissions::{ANY_ROLE, RECORD_EDITORS};
+use crate::auth::session::SessionState;
+use crate::database::audit::{record_change, snapshot, Operation};
+use crate::database::blotter::{case_events, parties, record_transition, replace_parties, resident_cases, stage_column};
+use crate::database::connection::{DbPool, ExecuteCached};
+use crate::database::household::RESIDENT_NAME_SQL;
+use crate::database::query::{fetch_page, Filters, Listing};
+use crate::database::recycle_bin::{restore, soft_delete};
+use crate::error::BmsError;
+use tauri::State;
+use crate::models::blotter::{Blotter, BlotterFilter, BlotterParty, CaseEvent, CaseStage, PartyRole, ResidentCase};
+use crate::models::query::{Page, PageRequest};
+use crate::validation::Validate;
+
+/// The Punong Barangay has 15 days from filing to mediate, and the Pangkat
+/// the 15 days after that to conciliate.
+const BLOTTER_COLUMNS: &str = "id, type_, reported_by, involved, incident_date, location, zone, status, narrative, action, witnesses, evidence, resolution, hearing_date, \
+    stage, filed_at, date(filed_at, '+15 days') AS mediation_deadline, date(filed_at, '+30 days') AS conciliation_deadline";
+
+const BLOTTER_LISTING: Listing = Listing {
+    table: "blotters",
+    columns: BLOTTER_COLUMNS,
+    sortable: &["incident_date", "status", "zone", "type_", "reported_by", "stage", "filed_at"],
+    default_sort: "incident_date",
+};
+
+fn blotter_from_row(row: &Row) -> rusqlite::Result<Blotter> {
+    Ok(Blotter {
+        id: row.get(0)?,
+        type_: row.get(1)?,
+        reported_by: row.get(2)?,
+        involved: row.get(3)?,
+        incident_date: row.get(4)?,
+        location: row.get(5)?,
+        zone: row.get(6)?,
+        status: row.get(7)?,
+        narrative: row.get(8)?,
+        action: row.get(9)?,
+        witnesses: row.get(10)?,
+        evidence: row.get(11)?,
+        resolution: row.get(12)?,
+        hearing_date: row.get(13)?,
+        parties: Vec::new(),
+        stage: stage_column(row, 14)?,
+        filed_at: row.get(15)?,
+        mediation_deadline: row.get(16)?,
+        conciliation_deadline: row.get(17)?,
+    })
+}
+
+/// Fills in the names of resident parties from their records, failing if
+/// one of them no longer exists.
+fn resolve_parties(conn: &Connection, parties: &[BlotterParty]) -> Result<Vec<BlotterParty>, BmsError> {
+    let mut resolved = Vec::with_capacity(parties.len());
+    for party in parties {
+        let mut party = party.clone();
+        if let Some(resident_id) = party.resident_id {
+            party.name = conn
+                .prepare_cached(&format!(
+                    "SELECT {} FROM residents r WHERE r.id = ?1 AND r.deleted_at IS NULL",
+                    RESIDENT_NAME_SQL
+                ))?
+                .query_row(params![resident_id], |row| row.get(0))
+                .optional()?
+                .ok_or_else(|| BmsError::invalid("parties", "One of the selected residents no longer exists"))?;
+        }
+        resolved.push(party);
+    }
+    Ok(resolved)
+}
+
+/// Names of the parties in `role`, as stored in the summary columns.
+fn names_in_role(parties: &[BlotterParty], role: PartyRole) -> String {
+    parties
+        .iter()
+        .filter(|p| p.role == role)
+        .map(|p| p.name.trim())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
 
 #[tauri::command]
-pub fn fetch_all_blotters_command() -> Result<Vec<Blotter>, String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn fetch_all_blotters_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String) -> Result<Vec<Blotter>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
 
-    let mut stmt = conn.prepare(
-        "SELECT id, type_, reported_by, involved, incident_date, location, zone, status, narrative, action, witnesses, evidence, resolution, hearing_date FROM blotters"
-    ).map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare_cached(
+        &format!("SELECT {} FROM blotters WHERE deleted_at IS NULL", BLOTTER_COLUMNS)
+    )?;
 
     let blotters_iter = stmt
-        .query_map([], |row| {
-            Ok(Blotter {
-                id: row.get(0)?,
-                type_: row.get(1)?,
-                reported_by: row.get(2)?,
-                involved: row.get(3)?,
-                incident_date: row.get(4)?,
-                location: row.get(5)?,
-                zone: row.get(6)?,
-                status: row.get(7)?,
-                narrative: row.get(8)?,
-                action: row.get(9)?,
-                witnesses: row.get(10)?,
-                evidence: row.get(11)?,
-                resolution: row.get(12)?,
-                hearing_date: row.get(13)?,
-            })
-        })
-        .map_err(|e| e.to_string())?;
+        .query_map([], blotter_from_row)?;
 
     let mut blotters = Vec::new();
     for blotter in blotters_iter {
-        blotters.push(blotter.map_err(|e| e.to_string())?);
+        let mut blotter = blotter?;
+        if let Some(id) = blotter.id {
+            blotter.parties = parties(&conn, id.into())?;
+        }
+        blotters.push(blotter);
     }
 
     Ok(blotters)
 }
 
 #[tauri::command]
-pub fn insert_blotter_command(blotter: Blotter) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn query_blotters_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, page: PageRequest, filter: Option<BlotterFilter>) -> Result<Page<Blotter>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
+    let filter = filter.unwrap_or_default();
+
+    let mut filters = Filters::new();
+    filters
+        .raw("deleted_at IS NULL")
+        .opt("zone = ?", filter.zone)
+        .opt("stage = ?", filter.stage.map(|s| s.as_str().to_string()))
+        .opt("type_ = ?", filter.type_)
+        .opt("id IN (SELECT blotter_id FROM blotter_parties WHERE resident_id = ?)", filter.resident_id)
+        .opt("date(incident_date) >= date(?)", filter.date_from)
+        .opt("date(incident_date) <= date(?)", filter.date_to);
+
+    let mut page = fetch_page(&conn, &BLOTTER_LISTING, &filters, &page, blotter_from_row)?;
+    for blotter in &mut page.rows {
+        if let Some(id) = blotter.id {
+            blotter.parties = parties(&conn, id.into())?;
+        }
+    }
+
+    Ok(page)
+}
 
-    conn.execute(
+/// A resident's history as complainant, respondent or witness.
+#[tauri::command]
+pub fn fetch_resident_cases_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, resident_id: i32) -> Result<Vec<ResidentCase>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
+
+    Ok(resident_cases(&conn, resident_id.into())?)
+}
+
+/// Cases still before the Punong Barangay or the Pangkat whose deadline has
+/// passed, the longest overdue first.
+#[tauri::command]
+pub fn fetch_overdue_blotters_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String) -> Result<Vec<Blotter>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
+
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT * FROM (SELECT {} FROM blotters WHERE deleted_at IS NULL)
+         WHERE (stage IN ('filed', 'mediation') AND mediation_deadline < date('now'))
+            OR (stage = 'conciliation' AND conciliation_deadline < date('now'))
+         ORDER BY filed_at, id",
+        BLOTTER_COLUMNS
+    ))?;
+
+    let blotters_iter = stmt.query_map([], blotter_from_row)?;
+
+    let mut blotters = Vec::new();
+    for blotter in blotters_iter {
+        let mut blotter = blotter?;
+        if let Some(id) = blotter.id {
+            blotter.parties = parties(&conn, id.into())?;
+        }
+        blotters.push(blotter);
+    }
+
+    Ok(blotters)
+}
+
+/// Stage changes and hearings of a case, oldest first.
+#[tauri::command]
+pub fn fetch_blotter_history_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, blotter_id: i32) -> Result<Vec<CaseEvent>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
+
+    Ok(case_events(&conn, blotter_id.into())?)
+}
+
+/// Moves a case to its next stage. Only the moves allowed by
+/// `CaseStage::can_move_to` are accepted.
+#[tauri::command]
+pub fn transition_blotter_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, blotter_id: i32, to: CaseStage, notes: Option<String>) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+
+    let from = tx
+        .prepare_cached("SELECT stage FROM blotters WHERE id = ?1 AND deleted_at IS NULL")?
+        .query_row(params![blotter_id], |row| stage_column(row, 0))
+        .optional()?
+        .ok_or_else(|| BmsError::not_found("Blotter not found"))?;
+    if !from.can_move_to(to) {
+        return Err(BmsError::conflict(format!(
+            "A case cannot go from {} to {}",
+            from.label(),
+            to.label()
+        )));
+    }
+
+    let before = snapshot(&tx, "blotters", blotter_id.into())?;
+    tx.execute_cached(
+        "UPDATE blotters SET stage = ?1, status = ?2 WHERE id = ?3",
+        params![to.as_str(), to.label(), blotter_id],
+    )?;
+    record_transition(&tx, &user, blotter_id.into(), Some(from), to, notes.as_deref().unwrap_or_default())?;
+    record_change(&tx, &user, "blotters", blotter_id.into(), Operation::Update, before)?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn insert_blotter_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, blotter: Blotter) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    blotter.validate()?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+    let parties = resolve_parties(&tx, &blotter.parties)?;
+
+    tx.execute_cached(
         "INSERT INTO blotters (
             type_,
             reported_by,
@@ -57,33 +232,48 @@ pub fn insert_blotter_command(blotter: Blotter) -> Result<(), String> {
             witnesses,
             evidence,
             resolution,
-            hearing_date
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            hearing_date,
+            stage,
+            filed_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, '', ?13, COALESCE(NULLIF(?14, ''), date('now')))",
         params![
             blotter.type_,
-            blotter.reported_by,
-            blotter.involved,
+            names_in_role(&parties, PartyRole::Complainant),
+            names_in_role(&parties, PartyRole::Respondent),
             blotter.incident_date,
             blotter.location,
-            blotter.zone,
-            blotter.status,
+            blotter.zone.trim(),
+            CaseStage::Filed.label(),
             blotter.narrative,
             blotter.action,
-            blotter.witnesses,
+            names_in_role(&parties, PartyRole::Witness),
             blotter.evidence,
             blotter.resolution,
-            blotter.hearing_date
+            CaseStage::Filed.as_str(),
+            blotter.filed_at
         ],
-    ).map_err(|e| e.to_string())?;
+    )?;
+
+    let id = tx.last_insert_rowid();
+    replace_parties(&tx, id, &parties)?;
+    record_transition(&tx, &user, id, None, CaseStage::Filed, "")?;
+    record_change(&tx, &user, "blotters", id, Operation::Create, None)?;
+    tx.commit()?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn update_blotter_command(blotter: Blotter) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn update_blotter_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, blotter: Blotter) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    blotter.validate()?;
+    let id = blotter.id.ok_or_else(|| BmsError::invalid("id", "Blotter id is required"))?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+    let before = snapshot(&tx, "blotters", id.into())?;
+    let parties = resolve_parties(&tx, &blotter.parties)?;
 
-    conn.execute(
+    let changed = tx.execute_cached(
         "UPDATE blotters SET
             type_ = ?1,
             reported_by = ?2,
@@ -91,50 +281,67 @@ pub fn update_blotter_command(blotter: Blotter) -> Result<(), String> {
             incident_date = ?4,
             location = ?5,
             zone = ?6,
-            status = ?7,
-            narrative = ?8,
-            action = ?9,
-            witnesses = ?10,
-            evidence = ?11,
-            resolution = ?12,
-            hearing_date = ?13
-         WHERE id = ?14",
+            narrative = ?7,
+            action = ?8,
+            witnesses = ?9,
+            evidence = ?10,
+            resolution = ?11,
+            filed_at = COALESCE(NULLIF(?12, ''), filed_at)
+         WHERE id = ?13 AND deleted_at IS NULL",
         params![
             blotter.type_,
-            blotter.reported_by,
-            blotter.involved,
+            names_in_role(&parties, PartyRole::Complainant),
+            names_in_role(&parties, PartyRole::Respondent),
             blotter.incident_date,
             blotter.location,
-            blotter.zone,
-            blotter.status,
+            blotter.zone.trim(),
             blotter.narrative,
             blotter.action,
-            blotter.witnesses,
+            names_in_role(&parties, PartyRole::Witness),
             blotter.evidence,
             blotter.resolution,
-            blotter.hearing_date,
+            blotter.filed_at,
             blotter.id
         ],
-    ).map_err(|e| e.to_string())?;
+    )?;
+    if changed == 0 {
+        return Err(BmsError::not_found("Blotter not found"));
+    }
+
+    replace_parties(&tx, id.into(), &parties)?;
+    record_change(&tx, &user, "blotters", id.into(), Operation::Update, before)?;
+    tx.commit()?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn save_blotter_command(blotter: Blotter) -> Result<(), String> {
+pub fn save_blotter_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, blotter: Blotter) -> Result<(), BmsError> {
     if blotter.id.is_some() {
-        update_blotter_command(blotter)
+        update_blotter_command(pool, session, token, blotter)
     } else {
-        insert_blotter_command(blotter)
+        insert_blotter_command(pool, session, token, blotter)
     }
 }
 
 #[tauri::command]
-pub fn delete_blotter_command(id: i32) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn delete_blotter_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    let conn = pool.get()?;
+
+    soft_delete(&conn, &user, "blotters", id.into())?;
 
-    conn.execute("DELETE FROM blotters WHERE id = ?", params![id])
-        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn restore_blotter_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    let conn = pool.get()?;
+
+    if !restore(&conn, &user, "blotters", id.into())? {
+        return Err(BmsError::not_found("Blotter is not in the recycle bin"));
+    }
 
     Ok(())
 }
@@ -0,0 +1,125 @@
+use crate::auth::permissions::{ANY_ROLE, TREASURY};
+use crate::auth::session::SessionState;
+use crate::database::audit::{record_change, snapshot, Operation};
+use crate::database::budget::{appropriation_balance, appropriation_from_row, APPROPRIATION_SELECT};
+use crate::database::connection::{DbPool, ExecuteCached};
+use crate::error::BmsError;
+use crate::models::budget::Appropriation;
+use crate::models::money::Money;
+use crate::validation::Validate;
+use rusqlite::params;
+use tauri::State;
+
+/// The appropriations of a fiscal year with what is left of each.
+#[tauri::command]
+pub fn fetch_appropriations_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, fiscal_year: i32) -> Result<Vec<Appropriation>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
+
+    let mut stmt = conn.prepare_cached(&format!(
+        "{} WHERE a.fiscal_year = ?2
+         ORDER BY CASE a.account_code
+            WHEN 'ps' THEN 0 WHEN 'mooe' THEN 1 WHEN 'co' THEN 2
+            WHEN 'development_fund' THEN 3 WHEN 'drrm_fund' THEN 4 ELSE 5
+         END",
+        APPROPRIATION_SELECT
+    ))?;
+    let appropriations = stmt
+        .query_map(params![None::<i64>, fiscal_year], appropriation_from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(appropriations)
+}
+
+/// One appropriation with its balance as of now.
+#[tauri::command]
+pub fn fetch_appropriation_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<Appropriation, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
+
+    appropriation_balance(&conn, id.into(), None)?.ok_or_else(|| BmsError::not_found("Appropriation not found"))
+}
+
+/// Adds or changes an appropriation and returns its id. Once expenses are
+/// charged to it, its amount cannot go below what they total and it cannot
+/// move to another year.
+#[tauri::command]
+pub fn save_appropriation_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, appropriation: Appropriation) -> Result<i64, BmsError> {
+    let user = session.require(&token, TREASURY)?;
+    appropriation.validate()?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+
+    let Some(id) = appropriation.id else {
+        tx.execute_cached(
+            "INSERT INTO appropriations (fiscal_year, account_code, description, amount) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                appropriation.fiscal_year,
+                appropriation.account_code.as_str(),
+                appropriation.description.trim(),
+                appropriation.amount
+            ],
+        )?;
+        let id = tx.last_insert_rowid();
+        record_change(&tx, &user, "appropriations", id, Operation::Create, None)?;
+        tx.commit()?;
+        return Ok(id);
+    };
+
+    let current = appropriation_balance(&tx, id.into(), None)?
+        .ok_or_else(|| BmsError::not_found("Appropriation not found"))?;
+    if current.obligated > appropriation.amount {
+        return Err(BmsError::conflict(format!(
+            "{} has already been charged to this appropriation",
+            current.obligated
+        )));
+    }
+    if current.obligated > Money::ZERO && current.fiscal_year != appropriation.fiscal_year {
+        return Err(BmsError::conflict(
+            "Expenses have been charged to this appropriation, so its fiscal year cannot change",
+        ));
+    }
+
+    let before = snapshot(&tx, "appropriations", id.into())?;
+    tx.execute_cached(
+        "UPDATE appropriations SET fiscal_year = ?1, account_code = ?2, description = ?3, amount = ?4 WHERE id = ?5",
+        params![
+            appropriation.fiscal_year,
+            appropriation.account_code.as_str(),
+            appropriation.description.trim(),
+            appropriation.amount,
+            id
+        ],
+    )?;
+    record_change(&tx, &user, "appropriations", id.into(), Operation::Update, before)?;
+    tx.commit()?;
+
+    Ok(id.into())
+}
+
+/// Appropriations that expenses have been charged to, even deleted ones,
+/// cannot be removed.
+#[tauri::command]
+pub fn delete_appropriation_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<(), BmsError> {
+    let user = session.require(&token, TREASURY)?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+
+    let before = snapshot(&tx, "appropriations", id.into())?;
+    if before.is_none() {
+        return Err(BmsError::not_found("Appropriation not found"));
+    }
+    let charged: bool = tx
+        .prepare_cached("SELECT EXISTS (SELECT 1 FROM expenses WHERE appropriation_id = ?1)")?
+        .query_row(params![id], |row| row.get(0))?;
+    if charged {
+        return Err(BmsError::conflict(
+            "Expenses have been charged to this appropriation, so it cannot be deleted; set its amount instead",
+        ));
+    }
+    tx.execute_cached("DELETE FROM appropriations WHERE id = ?1", params![id])?;
+    record_change(&tx, &user, "appropriations", id.into(), Operation::Delete, before)?;
+    tx.commit()?;
+
+    Ok(())
+}
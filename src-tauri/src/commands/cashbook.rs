@@ -0,0 +1,358 @@
+use crate::auth::permissions::{ADMINISTRATORS, ANY_ROLE, TREASURY};
+use crate::auth::session::SessionState;
+use crate::database::audit::{record_change, snapshot, Operation};
+use crate::database::blotter::parse_column;
+use crate::database::cashbook::{
+    balance_through, closed_through, deposits_through, ensure_open, ensure_row_open, receipts_through,
+    FUND_DEPOSITS, FUND_DISBURSEMENTS, FUND_RECEIPTS, SINCE_OPENING,
+};
+use crate::database::connection::{DbPool, ExecuteCached};
+use crate::error::BmsError;
+use crate::models::cashbook::{
+    BankDeposit, BankReconciliation, Cashbook, CashbookClosing, CashbookEntry, CashbookEntryKind, Fund, OpeningBalance,
+};
+use crate::models::money::Money;
+use crate::models::report::ReportPeriod;
+use crate::validation::{Validate, Validator};
+use rusqlite::{params, OptionalExtension, Row};
+use tauri::State;
+
+fn deposit_from_row(row: &Row) -> rusqlite::Result<BankDeposit> {
+    Ok(BankDeposit {
+        id: row.get(0)?,
+        fund: parse_column(row, 1)?,
+        date: row.get(2)?,
+        amount: row.get(3)?,
+        reference: row.get(4)?,
+        cleared_date: row.get(5)?,
+    })
+}
+
+#[tauri::command]
+pub fn fetch_opening_balances_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String) -> Result<Vec<OpeningBalance>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
+
+    let mut stmt = conn.prepare_cached("SELECT fund, as_of, amount FROM fund_opening_balances ORDER BY id")?;
+    let balances = stmt
+        .query_map([], |row| {
+            Ok(OpeningBalance {
+                fund: parse_column(row, 0)?,
+                as_of: row.get(1)?,
+                amount: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(balances)
+}
+
+/// Sets where a fund's cashbook starts. Neither the old nor the new date may
+/// fall in a closed period.
+#[tauri::command]
+pub fn save_opening_balance_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, balance: OpeningBalance) -> Result<(), BmsError> {
+    let user = session.require(&token, TREASURY)?;
+    balance.validate()?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+
+    ensure_open(&tx, &balance.as_of)?;
+    let current: Option<(i64, String)> = tx
+        .prepare_cached("SELECT id, as_of FROM fund_opening_balances WHERE fund = ?1")?
+        .query_row(params![balance.fund.as_str()], |row| Ok((row.get(0)?, row.get(1)?)))
+        .optional()?;
+
+    match current {
+        Some((id, as_of)) => {
+            ensure_open(&tx, &as_of)?;
+            let before = snapshot(&tx, "fund_opening_balances", id)?;
+            tx.execute_cached(
+                "UPDATE fund_opening_balances SET as_of = ?1, amount = ?2 WHERE id = ?3",
+                params![balance.as_of, balance.amount, id],
+            )?;
+            record_change(&tx, &user, "fund_opening_balances", id, Operation::Update, before)?;
+        }
+        None => {
+            tx.execute_cached(
+                "INSERT INTO fund_opening_balances (fund, as_of, amount) VALUES (?1, ?2, ?3)",
+                params![balance.fund.as_str(), balance.as_of, balance.amount],
+            )?;
+            let id = tx.last_insert_rowid();
+            record_change(&tx, &user, "fund_opening_balances", id, Operation::Create, None)?;
+        }
+    }
+    tx.commit()?;
+
+    Ok(())
+}
+
+/// A fund's receipts and disbursements in date order, each with the
+/// balance after it.
+#[tauri::command]
+pub fn fetch_cashbook_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, fund: Fund, period: ReportPeriod) -> Result<Cashbook, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    period.validate()?;
+    let conn = pool.get()?;
+
+    let day_before: String = conn
+        .prepare_cached("SELECT date(?1, '-1 day')")?
+        .query_row(params![period.date_from], |row| row.get(0))?;
+    let balance_forward = balance_through(&conn, fund, &day_before)?;
+
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT * FROM (
+            SELECT 'receipt' AS kind, id, date, or_number, received_from || ' - ' || category AS particulars,
+                   amount, 0 AS sort, date(date) AS day
+            {receipts} AND {since}
+            UNION ALL
+            SELECT 'disbursement', id, date, or_number, paid_to || ' - ' || category, amount, 1, date(date)
+            {disbursements} AND {since}
+        )
+        WHERE day BETWEEN date(?2) AND date(?3)
+        ORDER BY day, sort, id",
+        receipts = FUND_RECEIPTS,
+        disbursements = FUND_DISBURSEMENTS,
+        since = SINCE_OPENING,
+    ))?;
+    let rows: Vec<(CashbookEntryKind, i32, String, i32, String, Money)> = stmt
+        .query_map(params![fund.as_str(), period.date_from, period.date_to], |row| {
+            let kind = match row.get::<_, String>(0)?.as_str() {
+                "receipt" => CashbookEntryKind::Receipt,
+                _ => CashbookEntryKind::Disbursement,
+            };
+            Ok((kind, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?))
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut balance = balance_forward;
+    let mut total_receipts = Money::ZERO;
+    let mut total_disbursements = Money::ZERO;
+    let entries = rows
+        .into_iter()
+        .map(|(kind, id, date, or_number, particulars, amount)| {
+            let (receipt, disbursement) = match kind {
+                CashbookEntryKind::Receipt => (amount, Money::ZERO),
+                CashbookEntryKind::Disbursement => (Money::ZERO, amount),
+            };
+            total_receipts += receipt;
+            total_disbursements += disbursement;
+            balance += receipt - disbursement;
+            CashbookEntry { kind, id, date, or_number, particulars, receipt, disbursement, balance }
+        })
+        .collect();
+
+    Ok(Cashbook {
+        fund,
+        period,
+        balance_forward,
+        entries,
+        total_receipts,
+        total_disbursements,
+        closing_balance: balance,
+        closed_through: closed_through(&conn)?,
+    })
+}
+
+#[tauri::command]
+pub fn fetch_cashbook_closing_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String) -> Result<Option<CashbookClosing>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
+
+    let closing = conn
+        .prepare_cached(
+            "SELECT closed_through, username, closed_at FROM cashbook_closings
+             ORDER BY closed_through DESC, id DESC LIMIT 1",
+        )?
+        .query_row([], |row| {
+            Ok(CashbookClosing {
+                closed_through: row.get(0)?,
+                username: row.get(1)?,
+                closed_at: row.get(2)?,
+            })
+        })
+        .optional()?;
+
+    Ok(closing)
+}
+
+/// Closes the books through `closed_through`. Incomes, expenses and
+/// deposits dated up to it are locked until the period is reopened.
+#[tauri::command]
+pub fn close_cashbook_period_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, closed_through: String) -> Result<(), BmsError> {
+    let user = session.require(&token, TREASURY)?;
+    Validator::new().date("closed_through", &closed_through).finish()?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+
+    let (future, already_closed): (bool, bool) = tx
+        .prepare_cached(
+            "SELECT date(?1) > date('now', 'localtime'),
+                    COALESCE(date(?1) <= (SELECT MAX(closed_through) FROM cashbook_closings), 0)",
+        )?
+        .query_row(params![closed_through], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    if future {
+        return Err(BmsError::invalid("closed_through", "The books cannot be closed through a future date"));
+    }
+    if already_closed {
+        return Err(BmsError::conflict("The books are already closed through that date"));
+    }
+
+    tx.execute_cached(
+        "INSERT INTO cashbook_closings (closed_through, user_id, username) VALUES (date(?1), ?2, ?3)",
+        params![closed_through, user.id, user.username],
+    )?;
+    let id = tx.last_insert_rowid();
+    record_change(&tx, &user, "cashbook_closings", id, Operation::Create, None)?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+/// Undoes the latest closing, unlocking the entries it covered.
+#[tauri::command]
+pub fn reopen_cashbook_period_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String) -> Result<(), BmsError> {
+    let user = session.require(&token, ADMINISTRATORS)?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+
+    let id: i64 = tx
+        .prepare_cached("SELECT id FROM cashbook_closings ORDER BY closed_through DESC, id DESC LIMIT 1")?
+        .query_row([], |row| row.get(0))
+        .optional()?
+        .ok_or_else(|| BmsError::not_found("The books have not been closed"))?;
+
+    let before = snapshot(&tx, "cashbook_closings", id)?;
+    tx.execute_cached("DELETE FROM cashbook_closings WHERE id = ?1", params![id])?;
+    record_change(&tx, &user, "cashbook_closings", id, Operation::Delete, before)?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+/// Records a deposit of a fund's collections and returns its id.
+#[tauri::command]
+pub fn record_bank_deposit_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, deposit: BankDeposit) -> Result<i64, BmsError> {
+    let user = session.require(&token, TREASURY)?;
+    deposit.validate()?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+
+    ensure_open(&tx, &deposit.date)?;
+    tx.execute_cached(
+        "INSERT INTO bank_deposits (fund, date, amount, reference) VALUES (?1, ?2, ?3, ?4)",
+        params![deposit.fund.as_str(), deposit.date, deposit.amount, deposit.reference.trim()],
+    )?;
+    let id = tx.last_insert_rowid();
+    record_change(&tx, &user, "bank_deposits", id, Operation::Create, None)?;
+    tx.commit()?;
+
+    Ok(id)
+}
+
+#[tauri::command]
+pub fn fetch_bank_deposits_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, fund: Fund, period: ReportPeriod) -> Result<Vec<BankDeposit>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    period.validate()?;
+    let conn = pool.get()?;
+
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT id, fund, date, amount, reference, cleared_date
+         {} AND date(date) BETWEEN date(?2) AND date(?3)
+         ORDER BY date, id",
+        FUND_DEPOSITS
+    ))?;
+    let deposits = stmt
+        .query_map(params![fund.as_str(), period.date_from, period.date_to], deposit_from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(deposits)
+}
+
+/// Marks a deposit as showing on the bank statement on `cleared_date`, or
+/// as not yet showing when it is None.
+#[tauri::command]
+pub fn clear_bank_deposit_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32, cleared_date: Option<String>) -> Result<(), BmsError> {
+    let user = session.require(&token, TREASURY)?;
+    if let Some(cleared_date) = &cleared_date {
+        Validator::new().date("cleared_date", cleared_date).finish()?;
+    }
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+
+    let before = snapshot(&tx, "bank_deposits", id.into())?;
+    if before.is_none() {
+        return Err(BmsError::not_found("Deposit not found"));
+    }
+    let too_early: bool = tx
+        .prepare_cached("SELECT COALESCE(date(?1) < date(date), 0) FROM bank_deposits WHERE id = ?2")?
+        .query_row(params![cleared_date, id], |row| row.get(0))?;
+    if too_early {
+        return Err(BmsError::invalid("cleared_date", "A deposit cannot clear before it was made"));
+    }
+
+    tx.execute_cached(
+        "UPDATE bank_deposits SET cleared_date = date(?1) WHERE id = ?2",
+        params![cleared_date, id],
+    )?;
+    record_change(&tx, &user, "bank_deposits", id.into(), Operation::Update, before)?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_bank_deposit_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<(), BmsError> {
+    let user = session.require(&token, TREASURY)?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+
+    let before = snapshot(&tx, "bank_deposits", id.into())?;
+    if before.is_none() {
+        return Err(BmsError::not_found("Deposit not found"));
+    }
+    ensure_row_open(&tx, "bank_deposits", id.into())?;
+    tx.execute_cached("DELETE FROM bank_deposits WHERE id = ?1", params![id])?;
+    record_change(&tx, &user, "bank_deposits", id.into(), Operation::Delete, before)?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+/// Reconciles a fund's cashbook balance at the end of `as_of` with the
+/// balance on its bank statement for that date.
+#[tauri::command]
+pub fn fetch_bank_reconciliation_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, fund: Fund, as_of: String, statement_balance: Money) -> Result<BankReconciliation, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    Validator::new().date("as_of", &as_of).finish()?;
+    let conn = pool.get()?;
+
+    let book_balance = balance_through(&conn, fund, &as_of)?;
+    let undeposited_collections = receipts_through(&conn, fund, &as_of)? - deposits_through(&conn, fund, &as_of)?;
+
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT id, fund, date, amount, reference, cleared_date
+         {} AND {} AND date(date) <= date(?2)
+            AND (cleared_date IS NULL OR date(cleared_date) > date(?2))
+         ORDER BY date, id",
+        FUND_DEPOSITS, SINCE_OPENING
+    ))?;
+    let deposits_in_transit: Vec<BankDeposit> = stmt
+        .query_map(params![fund.as_str(), as_of], deposit_from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let in_transit: Money = deposits_in_transit.iter().map(|d| d.amount).sum();
+    let adjusted_bank_balance = statement_balance + in_transit + undeposited_collections;
+    let difference = book_balance - adjusted_bank_balance;
+
+    Ok(BankReconciliation {
+        fund,
+        as_of,
+        book_balance,
+        statement_balance,
+        undeposited_collections,
+        deposits_in_transit,
+        adjusted_bank_balance,
+        difference,
+        reconciled: difference == Money::ZERO,
+    })
+}
@@ -1,99 +1,580 @@
-use rusqlite::params;
-use crate::database::connection::establish_connection;
-use crate::models::certificate::Certificate; 
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use crate::auth::permissions::{ANY_ROLE, CERTIFICATE_ISSUERS};
+use crate::auth::session::SessionState;
+use crate::database::audit::{record_change, snapshot, Operation};
+use crate::database::blotter::resident_cases;
+use crate::database::cashbook::{ensure_open, ensure_row_open};
+use crate::database::certificate::{next_control_number, open_payload, sign_certificate, signing_key};
+use crate::database::connection::{DbPool, ExecuteCached};
+use crate::database::household::RESIDENT_NAME_SQL;
+use crate::database::income::{insert_income, next_or_number, void_income};
+use crate::database::query::{fetch_page, Filters, Listing};
+use crate::database::recycle_bin::{restore, soft_delete};
+use crate::error::BmsError;
+use tauri::State;
+use crate::models::blotter::{PartyRole, ResidentCase};
+use crate::models::cashbook::Fund;
+use crate::models::certificate::{
+    is_clearance, Certificate, CertificateFilter, CertificatePayload, CertificatePrint, CertificateVerification, ClearanceEligibility,
+    VerificationStatus,
+};
+use crate::models::income::{Income, CERTIFICATE_INCOME_CATEGORY};
+use crate::models::money::Money;
+use crate::models::query::{Page, PageRequest};
+use crate::validation::{Validate, Validator};
 
+const CERTIFICATE_COLUMNS: &str = "id, resident_id, resident_name, type_, age, civil_status, ownership_text, amount, issued_date, clearance_override, control_number, signed_payload, \
+    (SELECT COUNT(*) FROM certificate_prints p WHERE p.certificate_id = certificates.id) AS print_count, \
+    income_id, (SELECT i.or_number FROM incomes i WHERE i.id = certificates.income_id) AS or_number, voided_at, void_reason, fee_override";
+
+const CERTIFICATE_LISTING: Listing = Listing {
+    table: "certificates",
+    columns: CERTIFICATE_COLUMNS,
+    sortable: &["issued_date", "resident_name", "type_", "control_number"],
+    default_sort: "issued_date",
+};
+
+fn certificate_from_row(row: &Row) -> rusqlite::Result<Certificate> {
+    Ok(Certificate {
+        id: row.get(0)?,
+        resident_id: row.get(1)?,
+        resident_name: row.get(2)?,
+        type_: row.get(3)?,
+        age: row.get(4)?,
+        civil_status: row.get(5)?,
+        ownership_text: row.get(6)?,
+        amount: row.get(7)?,
+        fee_override: row.get(17)?,
+        issued_date: row.get(8)?,
+        clearance_override: row.get(9)?,
+        control_number: row.get(10)?,
+        qr_payload: row.get(11)?,
+        print_count: row.get(12)?,
+        income_id: row.get(13)?,
+        or_number: row.get(14)?,
+        voided_at: row.get(15)?,
+        void_reason: row.get(16)?,
+    })
+}
+
+/// The resident's name, age on `issued_date` (today if unset) and civil
+/// status, as printed on a certificate.
+fn resident_details(conn: &Connection, resident_id: i32, issued_date: Option<&str>) -> Result<(String, i32, String), BmsError> {
+    conn.prepare_cached(&format!(
+        "SELECT {},
+                strftime('%Y', d.day) - strftime('%Y', r.date_of_birth)
+                    - (strftime('%m-%d', d.day) < strftime('%m-%d', r.date_of_birth)),
+                r.civil_status
+         FROM residents r, (SELECT date(COALESCE(?2, 'now')) AS day) d
+         WHERE r.id = ?1 AND r.deleted_at IS NULL",
+        RESIDENT_NAME_SQL
+    ))?
+    .query_row(params![resident_id, issued_date], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+    .optional()?
+    .ok_or_else(|| BmsError::invalid("resident_id", "The selected resident no longer exists"))
+}
+
+/// The scheduled fee for a certificate type, zero if it has none.
+fn scheduled_fee(conn: &Connection, type_: &str) -> rusqlite::Result<Money> {
+    Ok(conn
+        .prepare_cached("SELECT amount FROM certificate_fees WHERE type_ = ?1")?
+        .query_row(params![type_.trim()], |row| row.get(0))
+        .optional()?
+        .unwrap_or_default())
+}
+
+/// The fee to charge: the scheduled one, or the amount given along with a
+/// reason for the difference, which is returned to keep with the certificate.
+fn check_fee(conn: &Connection, cert: &Certificate) -> Result<(Money, Option<String>), BmsError> {
+    let scheduled = scheduled_fee(conn, &cert.type_)?;
+    let amount = match cert.amount {
+        Some(amount) if amount != scheduled => amount,
+        _ => return Ok((scheduled, None)),
+    };
+
+    let reason = cert.fee_override.as_deref().map(str::trim).unwrap_or_default();
+    if reason.is_empty() {
+        return Err(BmsError::invalid(
+            "fee_override",
+            format!("The scheduled fee is {}; give a reason to charge {} instead", scheduled, amount),
+        ));
+    }
+    Ok((amount, Some(reason.to_string())))
+}
+
+/// Open cases in which the resident is a respondent.
+fn blocking_cases(conn: &Connection, resident_id: i32) -> rusqlite::Result<Vec<ResidentCase>> {
+    Ok(resident_cases(conn, resident_id.into())?
+        .into_iter()
+        .filter(|c| c.pending && c.role == PartyRole::Respondent)
+        .collect())
+}
+
+/// Refuses a clearance for a resident with pending cases unless an override
+/// reason is given. Returns the reason and the overridden case ids to store
+/// with the certificate, if any.
+fn check_clearance(conn: &Connection, resident_id: i32, cert: &Certificate) -> Result<(Option<String>, Option<String>), BmsError> {
+    let cases = blocking_cases(conn, resident_id)?;
+    if cases.is_empty() {
+        return Ok((None, None));
+    }
+
+    let reason = cert.clearance_override.as_deref().map(str::trim).unwrap_or_default();
+    if reason.is_empty() {
+        return Err(BmsError::conflict(format!(
+            "The resident is a respondent in {} pending case(s); give a reason to issue the clearance anyway",
+            cases.len()
+        )));
+    }
+    let case_ids: Vec<String> = cases.iter().map(|c| c.blotter_id.to_string()).collect();
+    Ok((Some(reason.to_string()), Some(case_ids.join(","))))
+}
+
+/// Whether a clearance can be issued to the resident without an override.
 #[tauri::command]
-pub fn fetch_all_certificates_command() -> Result<Vec<Certificate>, String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
-
-    let mut stmt = conn.prepare(
-        "SELECT id, resident_name, type_, age, civil_status, ownership_text, amount, issued_date FROM certificates"
-    ).map_err(|e| e.to_string())?;
-
-    let certs_iter = stmt.query_map([], |row| {
-        Ok(Certificate {
-            id: row.get(0)?,
-            resident_name: row.get(1)?,
-            type_: row.get(2)?,
-            age: row.get(3).ok(),
-            civil_status: row.get(4)?,
-            ownership_text: row.get(5)?,
-            amount: row.get(6)?,
-            issued_date: row.get(7)?,
-        })
-    }).map_err(|e| e.to_string())?;
+pub fn check_clearance_eligibility_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, resident_id: i32) -> Result<ClearanceEligibility, BmsError> {
+    session.require(&token, CERTIFICATE_ISSUERS)?;
+    let conn = pool.get()?;
+
+    let blocking_cases = blocking_cases(&conn, resident_id)?;
+    Ok(ClearanceEligibility {
+        resident_id,
+        eligible: blocking_cases.is_empty(),
+        blocking_cases,
+    })
+}
+
+#[tauri::command]
+pub fn fetch_all_certificates_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String) -> Result<Vec<Certificate>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
+
+    let mut stmt = conn.prepare_cached(
+        &format!("SELECT {} FROM certificates WHERE deleted_at IS NULL", CERTIFICATE_COLUMNS)
+    )?;
+
+    let certs_iter = stmt.query_map([], certificate_from_row)?;
+
+    let mut certs = Vec::new();
+    for cert in certs_iter {
+        certs.push(cert?);
+    }
+
+    Ok(certs)
+}
+
+/// Every certificate issued to a resident, newest first.
+#[tauri::command]
+pub fn fetch_certificates_by_resident_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, resident_id: i32) -> Result<Vec<Certificate>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
+
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {} FROM certificates WHERE resident_id = ?1 AND deleted_at IS NULL ORDER BY issued_date DESC, id DESC",
+        CERTIFICATE_COLUMNS
+    ))?;
+
+    let certs_iter = stmt.query_map(params![resident_id], certificate_from_row)?;
 
     let mut certs = Vec::new();
     for cert in certs_iter {
-        certs.push(cert.map_err(|e| e.to_string())?);
+        certs.push(cert?);
     }
 
     Ok(certs)
 }
 
 #[tauri::command]
-pub fn insert_certificate_command(cert: Certificate) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn query_certificates_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, page: PageRequest, filter: Option<CertificateFilter>) -> Result<Page<Certificate>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
+    let filter = filter.unwrap_or_default();
+
+    let mut filters = Filters::new();
+    filters
+        .raw("deleted_at IS NULL")
+        .opt("type_ = ?", filter.type_)
+        .opt("resident_id = ?", filter.resident_id)
+        .opt("resident_name = ?", filter.resident_name)
+        .opt("control_number = ?", filter.control_number)
+        .opt("date(issued_date) >= date(?)", filter.date_from)
+        .opt("date(issued_date) <= date(?)", filter.date_to);
+
+    fetch_page(&conn, &CERTIFICATE_LISTING, &filters, &page, certificate_from_row)
+}
+
+/// Looks up an issued certificate by the control number printed on it, e.g.
+/// when another office asks whether a paper is genuine.
+#[tauri::command]
+pub fn fetch_certificate_by_control_number_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, control_number: String) -> Result<Certificate, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
+
+    let cert = conn
+        .prepare_cached(&format!(
+            "SELECT {} FROM certificates WHERE control_number = ?1 AND deleted_at IS NULL",
+            CERTIFICATE_COLUMNS
+        ))?
+        .query_row(params![control_number.trim().to_uppercase()], certificate_from_row)
+        .optional()?;
+
+    cert.ok_or_else(|| BmsError::not_found(format!("No certificate has control number {}", control_number.trim())))
+}
+
+/// Checks a scanned QR payload: that this installation signed it, and that
+/// the certificate it names is still on record with the same details.
+#[tauri::command]
+pub fn verify_certificate_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, payload: String) -> Result<CertificateVerification, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
+
+    // Nothing can verify before this installation has signed anything.
+    let opened = signing_key(&conn)?
+        .ok_or(VerificationStatus::BadSignature)
+        .and_then(|key| open_payload(&key, &payload));
+    let claimed = match opened {
+        Ok(claimed) => claimed,
+        Err(status) => {
+            return Ok(CertificateVerification { status, valid: false, payload: None, certificate: None });
+        }
+    };
+
+    let found = conn
+        .prepare_cached(&format!(
+            "SELECT {}, deleted_at IS NOT NULL OR voided_at IS NOT NULL FROM certificates WHERE control_number = ?1",
+            CERTIFICATE_COLUMNS
+        ))?
+        .query_row(params![claimed.control_number], |row| Ok((certificate_from_row(row)?, row.get::<_, bool>(18)?)))
+        .optional()?;
+
+    let (status, certificate) = match found {
+        None => (VerificationStatus::NotFound, None),
+        Some((cert, true)) => (VerificationStatus::Revoked, Some(cert)),
+        Some((cert, false)) => {
+            let on_record = CertificatePayload {
+                control_number: cert.control_number.clone(),
+                resident_id: cert.resident_id,
+                resident_name: cert.resident_name.clone(),
+                type_: cert.type_.clone(),
+                issued_date: cert.issued_date.clone(),
+            };
+            let status = if on_record == claimed { VerificationStatus::Valid } else { VerificationStatus::Mismatch };
+            (status, Some(cert))
+        }
+    };
+
+    Ok(CertificateVerification {
+        status,
+        valid: status == VerificationStatus::Valid,
+        payload: Some(claimed),
+        certificate,
+    })
+}
+
+/// Logs a print of a certificate. Every print after the first is a reprint
+/// and should be marked as such on the paper.
+#[tauri::command]
+pub fn record_certificate_print_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<CertificatePrint, BmsError> {
+    let user = session.require(&token, CERTIFICATE_ISSUERS)?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+
+    let control_number: Option<String> = tx
+        .prepare_cached("SELECT control_number FROM certificates WHERE id = ?1 AND deleted_at IS NULL")?
+        .query_row(params![id], |row| row.get(0))
+        .optional()?
+        .ok_or_else(|| BmsError::not_found("Certificate not found"))?;
+
+    tx.execute_cached(
+        "INSERT INTO certificate_prints (certificate_id, user_id, username) VALUES (?1, ?2, ?3)",
+        params![id, user.id, user.username],
+    )?;
+    let (print_count, first_printed_at): (i32, String) = tx
+        .prepare_cached("SELECT COUNT(*), MIN(printed_at) FROM certificate_prints WHERE certificate_id = ?1")?
+        .query_row(params![id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    tx.commit()?;
+
+    Ok(CertificatePrint {
+        certificate_id: id,
+        control_number,
+        print_count,
+        is_reprint: print_count > 1,
+        first_printed_at,
+    })
+}
+
+#[tauri::command]
+pub fn insert_certificate_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, cert: Certificate) -> Result<(), BmsError> {
+    let user = session.require(&token, CERTIFICATE_ISSUERS)?;
+    cert.validate()?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+    let resident_id = cert
+        .resident_id
+        .ok_or_else(|| BmsError::invalid("resident_id", "Choose the resident this certificate is for"))?;
+    let (resident_name, age, civil_status) = resident_details(&tx, resident_id, cert.issued_date.as_deref())?;
+    let (clearance_override, overridden_cases) = if is_clearance(&cert.type_) {
+        check_clearance(&tx, resident_id, &cert)?
+    } else {
+        (None, None)
+    };
+    let control_number = next_control_number(&tx, &cert.type_, cert.issued_date.as_deref())?;
+
+    // A paid certificate is receipted in the same transaction, so the
+    // treasurer's ledger always matches what was issued.
+    let (amount, fee_override) = check_fee(&tx, &cert)?;
+    let income_id = if amount > Money::ZERO {
+        let date: String = tx
+            .prepare_cached("SELECT date(COALESCE(?1, 'now'))")?
+            .query_row(params![cert.issued_date], |row| row.get(0))?;
+        ensure_open(&tx, &date)?;
+        let receipt = next_or_number(&tx, user.id)?
+            .ok_or_else(|| BmsError::conflict("No OR booklet with unused receipts is assigned to you"))?;
+        let income = Income {
+            id: None,
+            type_: cert.type_.trim().to_string(),
+            amount,
+            or_number: receipt.or_number,
+            received_from: resident_name.clone(),
+            received_by: user.username.clone(),
+            category: CERTIFICATE_INCOME_CATEGORY.to_string(),
+            date,
+            fund: Fund::General,
+            voided_at: None,
+            void_reason: None,
+        };
+        let income_id = insert_income(&tx, &income, receipt.booklet_id)?;
+        record_change(&tx, &user, "incomes", income_id, Operation::Create, None)?;
+        Some(income_id)
+    } else {
+        None
+    };
 
-    conn.execute(
+    tx.execute_cached(
         "INSERT INTO certificates (
-            resident_name, type_, age, civil_status, ownership_text, amount, issued_date
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            resident_id, resident_name, type_, age, civil_status, ownership_text, amount, issued_date,
+            clearance_override, clearance_override_cases, control_number, income_id, fee_override
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
         params![
-            cert.resident_name,
+            resident_id,
+            resident_name,
             cert.type_,
-            cert.age,
-            cert.civil_status,
+            age,
+            civil_status,
             cert.ownership_text,
-            cert.amount,
-            cert.issued_date
+            amount,
+            cert.issued_date,
+            clearance_override,
+            overridden_cases,
+            control_number,
+            income_id,
+            fee_override
         ],
-    ).map_err(|e| e.to_string())?;
+    )?;
+
+    let id = tx.last_insert_rowid();
+    sign_certificate(&tx, id)?;
+    record_change(&tx, &user, "certificates", id, Operation::Create, None)?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+/// Control numbers run per type and year, so a numbered certificate keeps
+/// the type and year its number was taken for. Its fee was charged for that
+/// type too. Void it and issue a new one instead.
+fn check_numbering(control_number: Option<&str>, current_type: &str, cert: &Certificate, new_year: &str) -> Result<(), BmsError> {
+    let Some(control_number) = control_number else {
+        return Ok(());
+    };
+    if !cert.type_.trim().eq_ignore_ascii_case(current_type.trim()) {
+        return Err(BmsError::invalid(
+            "type_",
+            format!("Certificate {} cannot change type; void it and issue a new one", control_number),
+        ));
+    }
+    if !control_number.starts_with(new_year) {
+        return Err(BmsError::invalid(
+            "issued_date",
+            format!("Certificate {} cannot move to another year; void it and issue a new one", control_number),
+        ));
+    }
     Ok(())
 }
 
 #[tauri::command]
-pub fn update_certificate_command(cert: Certificate) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
-
-    conn.execute(
-        "UPDATE certificates SET
-            resident_name = ?1,
-            type_ = ?2,
-            age = ?3,
-            civil_status = ?4,
-            ownership_text = ?5,
-            amount = ?6,
-            issued_date = ?7
-        WHERE id = ?8",
-        params![
-            cert.resident_name,
-            cert.type_,
-            cert.age,
-            cert.civil_status,
-            cert.ownership_text,
-            cert.amount,
-            cert.issued_date,
-            cert.id
-        ],
-    ).map_err(|e| e.to_string())?;
+pub fn update_certificate_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, cert: Certificate) -> Result<(), BmsError> {
+    let user = session.require(&token, CERTIFICATE_ISSUERS)?;
+    cert.validate()?;
+    let id = cert.id;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+    let before = snapshot(&tx, "certificates", id.into())?;
+
+    // Turning a certificate into a clearance, or moving a clearance to
+    // another resident, is checked the same way as issuing one.
+    let (current_type, current_resident, voided, control_number, new_year): (String, Option<i32>, bool, Option<String>, String) = tx
+        .prepare_cached(
+            "SELECT type_, resident_id, voided_at IS NOT NULL, control_number,
+                    COALESCE(strftime('%Y', ?2), strftime('%Y', 'now'))
+             FROM certificates WHERE id = ?1 AND deleted_at IS NULL",
+        )?
+        .query_row(params![id, cert.issued_date], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+        })
+        .optional()?
+        .ok_or_else(|| BmsError::not_found("Certificate not found"))?;
+    if voided {
+        return Err(BmsError::conflict("A voided certificate cannot be edited"));
+    }
+    check_numbering(control_number.as_deref(), &current_type, &cert, &new_year)?;
+    let resident_changed = cert.resident_id.is_some() && cert.resident_id != current_resident;
+    if is_clearance(&cert.type_) && (!is_clearance(&current_type) || resident_changed) {
+        if let Some(resident_id) = cert.resident_id.or(current_resident) {
+            let (clearance_override, overridden_cases) = check_clearance(&tx, resident_id, &cert)?;
+            tx.execute_cached(
+                "UPDATE certificates SET clearance_override = ?1, clearance_override_cases = ?2 WHERE id = ?3",
+                params![clearance_override, overridden_cases, id],
+            )?;
+        }
+    }
+
+    let changed = tx.execute_cached(
+        "UPDATE certificates SET type_ = ?1, ownership_text = ?2, issued_date = ?3 WHERE id = ?4 AND deleted_at IS NULL",
+        params![cert.type_, cert.ownership_text, cert.issued_date, cert.id],
+    )?;
+    if changed == 0 {
+        return Err(BmsError::not_found("Certificate not found"));
+    }
+
+    // The resident details stay as they were at issuance unless the
+    // certificate is being moved to a different resident.
+    if let Some(resident_id) = cert.resident_id.filter(|r| Some(*r) != current_resident) {
+        let (resident_name, age, civil_status) = resident_details(&tx, resident_id, cert.issued_date.as_deref())?;
+        tx.execute_cached(
+            "UPDATE certificates SET resident_id = ?1, resident_name = ?2, age = ?3, civil_status = ?4 WHERE id = ?5",
+            params![resident_id, resident_name, age, civil_status, id],
+        )?;
+    }
+
+    // Copies printed before this change will no longer verify.
+    sign_certificate(&tx, id.into())?;
+    record_change(&tx, &user, "certificates", id.into(), Operation::Update, before)?;
+    tx.commit()?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn save_certificate_command(cert: Certificate) -> Result<(), String> {
+pub fn save_certificate_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, cert: Certificate) -> Result<(), BmsError> {
     if cert.id != 0 {
-        update_certificate_command(cert)
+        update_certificate_command(pool, session, token, cert)
     } else {
-        insert_certificate_command(cert)
+        insert_certificate_command(pool, session, token, cert)
+    }
+}
+
+/// Cancels an issued certificate. Its control number stays used, it no
+/// longer verifies, and the income recorded for its fee is voided with it.
+#[tauri::command]
+pub fn void_certificate_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32, reason: String) -> Result<(), BmsError> {
+    let user = session.require(&token, CERTIFICATE_ISSUERS)?;
+    Validator::new().required("reason", &reason).finish()?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+
+    let (control_number, income_id, voided): (Option<String>, Option<i64>, bool) = tx
+        .prepare_cached(
+            "SELECT control_number, income_id, voided_at IS NOT NULL FROM certificates WHERE id = ?1 AND deleted_at IS NULL",
+        )?
+        .query_row(params![id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .optional()?
+        .ok_or_else(|| BmsError::not_found("Certificate not found"))?;
+    if voided {
+        return Err(BmsError::conflict("This certificate has already been voided"));
+    }
+
+    let before = snapshot(&tx, "certificates", id.into())?;
+    tx.execute_cached(
+        "UPDATE certificates SET voided_at = datetime('now'), void_reason = ?1 WHERE id = ?2",
+        params![reason.trim(), id],
+    )?;
+    record_change(&tx, &user, "certificates", id.into(), Operation::Update, before)?;
+
+    if let Some(income_id) = income_id {
+        ensure_row_open(&tx, "incomes", income_id)?;
+        let before = snapshot(&tx, "incomes", income_id)?;
+        let income_reason = format!(
+            "Certificate {} voided: {}",
+            control_number.as_deref().unwrap_or_default(),
+            reason.trim()
+        );
+        if void_income(&tx, income_id, &income_reason)? {
+            record_change(&tx, &user, "incomes", income_id, Operation::Update, before)?;
+        }
     }
+    tx.commit()?;
+
+    Ok(())
 }
 
+/// Paid certificates have to be voided instead, so their income is reversed.
 #[tauri::command]
-pub fn delete_certificate_command(id: i32) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
-    conn.execute("DELETE FROM certificates WHERE id = ?", params![id])
-        .map_err(|e| e.to_string())?;
+pub fn delete_certificate_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<(), BmsError> {
+    let user = session.require(&token, CERTIFICATE_ISSUERS)?;
+    let conn = pool.get()?;
+
+    let paid: bool = conn
+        .prepare_cached(
+            "SELECT EXISTS (SELECT 1 FROM certificates c JOIN incomes i ON i.id = c.income_id
+                            WHERE c.id = ?1 AND i.voided_at IS NULL AND i.deleted_at IS NULL)",
+        )?
+        .query_row(params![id], |row| row.get(0))?;
+    if paid {
+        return Err(BmsError::conflict("This certificate has been paid for; void it so its receipt is reversed"));
+    }
+
+    soft_delete(&conn, &user, "certificates", id.into())?;
+
     Ok(())
-}
\ No newline at end of file
+}
+
+#[tauri::command]
+pub fn restore_certificate_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<(), BmsError> {
+    let user = session.require(&token, CERTIFICATE_ISSUERS)?;
+    let conn = pool.get()?;
+
+    if !restore(&conn, &user, "certificates", id.into())? {
+        return Err(BmsError::not_found("Certificate is not in the recycle bin"));
+    }
+
+    Ok(())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn certificate(type_: &str) -> Certificate {
+        serde_json::from_value(serde_json::json!({ "id": 1, "resident_id": 1, "type_": type_ })).unwrap()
+    }
+
+    #[test]
+    fn unnumbered_certificates_may_change() {
+        assert!(check_numbering(None, "Residency", &certificate("Barangay Clearance"), "2027").is_ok());
+    }
+
+    #[test]
+    fn numbered_certificates_keep_their_type() {
+        let number = Some("2026-RES-000001");
+        assert!(check_numbering(number, "Residency", &certificate(" residency "), "2026").is_ok());
+        let err = check_numbering(number, "Residency", &certificate("Barangay Clearance"), "2026").unwrap_err();
+        assert!(matches!(err, BmsError::Validation { fields, .. } if fields[0].field == "type_"));
+    }
+
+    #[test]
+    fn numbered_certificates_keep_their_year() {
+        let number = Some("2026-RES-000001");
+        let err = check_numbering(number, "Residency", &certificate("Residency"), "2027").unwrap_err();
+        assert!(matches!(err, BmsError::Validation { fields, .. } if fields[0].field == "issued_date"));
+    }
+}
@@ -1,14 +1,22 @@
 use rusqlite::params;
-use crate::database::connection::establish_connection;
+use crate::auth::permissions::{ANY_ROLE, RECORD_EDITORS};
+use crate::auth::session::SessionState;
+use crate::database::audit::{record_change, snapshot, Operation};
+use crate::database::connection::{DbPool, ExecuteCached};
+use crate::database::recycle_bin::{restore, soft_delete};
+use crate::error::BmsError;
+use tauri::State;
 use crate::models::event::Event;
+use crate::validation::Validate;
 
 #[tauri::command]
-pub fn fetch_all_events_command() -> Result<Vec<Event>, String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn fetch_all_events_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String) -> Result<Vec<Event>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
 
-    let mut stmt = conn.prepare(
-        "SELECT id, name, type_, status, date, venue, attendee, notes FROM events"
-    ).map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, name, type_, status, date, venue, attendee, notes FROM events WHERE deleted_at IS NULL"
+    )?;
 
     let events_iter = stmt
         .query_map([], |row| {
@@ -22,22 +30,24 @@ pub fn fetch_all_events_command() -> Result<Vec<Event>, String> {
                 attendee: row.get(6)?,
                 notes: row.get(7)?,
             })
-        })
-        .map_err(|e| e.to_string())?;
+        })?;
 
     let mut events = Vec::new();
     for event in events_iter {
-        events.push(event.map_err(|e| e.to_string())?);
+        events.push(event?);
     }
 
     Ok(events)
 }
 
 #[tauri::command]
-pub fn insert_event_command(event: Event) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn insert_event_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, event: Event) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    event.validate()?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
 
-    conn.execute(
+    tx.execute_cached(
         "INSERT INTO events (
             name,
             type_,
@@ -56,16 +66,24 @@ pub fn insert_event_command(event: Event) -> Result<(), String> {
             event.attendee,
             event.notes
         ],
-    ).map_err(|e| e.to_string())?;
+    )?;
+
+    record_change(&tx, &user, "events", tx.last_insert_rowid(), Operation::Create, None)?;
+    tx.commit()?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn update_event_command(event: Event) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn update_event_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, event: Event) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    event.validate()?;
+    let id = event.id.ok_or_else(|| BmsError::invalid("id", "Event id is required"))?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+    let before = snapshot(&tx, "events", id.into())?;
 
-    conn.execute(
+    let changed = tx.execute_cached(
         "UPDATE events SET
             name = ?1,
             type_ = ?2,
@@ -74,7 +92,7 @@ pub fn update_event_command(event: Event) -> Result<(), String> {
             venue = ?5,
             attendee = ?6,
             notes = ?7
-         WHERE id = ?8",
+         WHERE id = ?8 AND deleted_at IS NULL",
         params![
             event.name,
             event.type_,
@@ -85,26 +103,44 @@ pub fn update_event_command(event: Event) -> Result<(), String> {
             event.notes,
             event.id
         ],
-    ).map_err(|e| e.to_string())?;
+    )?;
+    if changed == 0 {
+        return Err(BmsError::not_found("Event not found"));
+    }
+
+    record_change(&tx, &user, "events", id.into(), Operation::Update, before)?;
+    tx.commit()?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn save_event_command(event: Event) -> Result<(), String> {
+pub fn save_event_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, event: Event) -> Result<(), BmsError> {
     if event.id.is_some() {
-        update_event_command(event)
+        update_event_command(pool, session, token, event)
     } else {
-        insert_event_command(event)
+        insert_event_command(pool, session, token, event)
     }
 }
 
 #[tauri::command]
-pub fn delete_event_command(id: i32) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn delete_event_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    let conn = pool.get()?;
+
+    soft_delete(&conn, &user, "events", id.into())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn restore_event_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    let conn = pool.get()?;
 
-    conn.execute("DELETE FROM events WHERE id = ?", params![id])
-        .map_err(|e| e.to_string())?;
+    if !restore(&conn, &user, "events", id.into())? {
+        return Err(BmsError::not_found("Event is not in the recycle bin"));
+    }
 
     Ok(())
 }
\ No newline at end of file
@@ -1,26 +1,89 @@
-use crate::database::connection::establish_connection;
+use crate::auth::permissions::{ANY_ROLE, TREASURY};
+use crate::auth::session::SessionState;
+use crate::database::audit::{record_change, snapshot, Operation};
+use crate::database::blotter::parse_column;
+use crate::database::budget::appropriation_balance;
+use crate::database::cashbook::{ensure_open, ensure_row_open};
+use crate::database::connection::{DbPool, ExecuteCached};
+use crate::database::recycle_bin::{restore, soft_delete};
+use crate::error::BmsError;
+use tauri::State;
 use crate::models::expense::Expense;
-use rusqlite::params;
+use crate::validation::Validate;
+use rusqlite::{params, Connection};
+
+/// An expense charged to an appropriation has to be paid from the fund the
+/// appropriation belongs to, fall in its fiscal year and fit in what is left
+/// of it, unless a reason for exceeding it is given.
+/// Returns the reason to keep with the expense.
+fn check_appropriation(conn: &Connection, expense: &Expense) -> Result<Option<String>, BmsError> {
+    let Some(appropriation_id) = expense.appropriation_id else {
+        return Ok(None);
+    };
+    let appropriation = appropriation_balance(conn, appropriation_id.into(), expense.id.map(Into::into))?
+        .ok_or_else(|| BmsError::invalid("appropriation_id", "The selected appropriation no longer exists"))?;
+
+    if expense.fund != appropriation.account_code.fund() {
+        return Err(BmsError::invalid(
+            "appropriation_id",
+            format!("The {} appropriation is not paid from this expense's fund", appropriation.account_code.label()),
+        ));
+    }
+
+    let year: Option<i32> = expense.date.get(..4).and_then(|y| y.parse().ok());
+    if year != Some(appropriation.fiscal_year) {
+        return Err(BmsError::invalid(
+            "appropriation_id",
+            format!("This appropriation is for fiscal year {}", appropriation.fiscal_year),
+        ));
+    }
+    if expense.amount <= appropriation.remaining {
+        return Ok(None);
+    }
+
+    match expense.overspend_override.as_deref().map(str::trim).filter(|r| !r.is_empty()) {
+        Some(reason) => Ok(Some(reason.to_string())),
+        None => Err(BmsError::conflict(format!(
+            "Only {} is left of the {} appropriation for {}; give a reason to exceed it",
+            appropriation.remaining,
+            appropriation.account_code.label(),
+            appropriation.fiscal_year
+        ))),
+    }
+}
+
+#[tauri::command]
+pub fn delete_expense_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<(), BmsError> {
+    let user = session.require(&token, TREASURY)?;
+    let conn = pool.get()?;
+    ensure_row_open(&conn, "expenses", id.into())?;
+
+    soft_delete(&conn, &user, "expenses", id.into())?;
+
+    Ok(())
+}
 
 #[tauri::command]
-pub fn delete_expense_command(id: i32) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn restore_expense_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<(), BmsError> {
+    let user = session.require(&token, TREASURY)?;
+    let conn = pool.get()?;
+    ensure_row_open(&conn, "expenses", id.into())?;
 
-    conn.execute(
-        "DELETE FROM expenses WHERE id = ?1",
-        params![id],
-    ).map_err(|e| e.to_string())?;
+    if !restore(&conn, &user, "expenses", id.into())? {
+        return Err(BmsError::not_found("Expense is not in the recycle bin"));
+    }
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn fetch_all_expenses_command() -> Result<Vec<Expense>, String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn fetch_all_expenses_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String) -> Result<Vec<Expense>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
 
     let mut stmt = conn
-        .prepare("SELECT id, type_, amount, or_number, paid_to, paid_by, category, date FROM expenses")
-        .map_err(|e| e.to_string())?;
+        .prepare_cached("SELECT id, type_, amount, or_number, paid_to, paid_by, category, date, appropriation_id, overspend_override, fund
+             FROM expenses WHERE deleted_at IS NULL")?;
 
     let expense_iter = stmt
         .query_map([], |row| {
@@ -33,25 +96,32 @@ pub fn fetch_all_expenses_command() -> Result<Vec<Expense>, String> {
                 paid_by: row.get(5)?,
                 category: row.get(6)?,
                 date: row.get(7)?,
+                fund: parse_column(row, 10)?,
+                appropriation_id: row.get(8)?,
+                overspend_override: row.get(9)?,
             })
-        })
-        .map_err(|e| e.to_string())?;
+        })?;
 
     let mut expenses = Vec::new();
     for expense in expense_iter {
-        expenses.push(expense.map_err(|e| e.to_string())?);
+        expenses.push(expense?);
     }
 
     Ok(expenses)
 }
 
 #[tauri::command]
-pub fn insert_expense_command(expense: Expense) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn insert_expense_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, expense: Expense) -> Result<(), BmsError> {
+    let user = session.require(&token, TREASURY)?;
+    expense.validate()?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+    ensure_open(&tx, &expense.date)?;
+    let overspend_override = check_appropriation(&tx, &expense)?;
 
-    conn.execute(
-        "INSERT INTO expenses (type_, amount, or_number, paid_to, paid_by, category, date)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    tx.execute_cached(
+        "INSERT INTO expenses (type_, amount, or_number, paid_to, paid_by, category, date, fund, appropriation_id, overspend_override)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
         params![
             expense.type_,
             expense.amount,
@@ -60,18 +130,37 @@ pub fn insert_expense_command(expense: Expense) -> Result<(), String> {
             expense.paid_by,
             expense.category,
             expense.date,
+            expense.fund.as_str(),
+            expense.appropriation_id,
+            overspend_override,
         ],
-    ).map_err(|e| e.to_string())?;
+    )?;
+
+    record_change(&tx, &user, "expenses", tx.last_insert_rowid(), Operation::Create, None)?;
+    tx.commit()?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn update_expense_command(expense: Expense) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn update_expense_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, expense: Expense) -> Result<(), BmsError> {
+    let user = session.require(&token, TREASURY)?;
+    expense.validate()?;
+    let id = expense.id.ok_or_else(|| BmsError::invalid("id", "Expense id is required"))?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+    let before = snapshot(&tx, "expenses", id.into())?;
+    if before.as_ref().is_none_or(|b| !b["deleted_at"].is_null()) {
+        return Err(BmsError::not_found("Expense not found"));
+    }
+    ensure_row_open(&tx, "expenses", id.into())?;
+    ensure_open(&tx, &expense.date)?;
+    let overspend_override = check_appropriation(&tx, &expense)?;
 
-    conn.execute(
-        "UPDATE expenses SET type_ = ?1, amount = ?2, or_number = ?3, paid_to = ?4, paid_by = ?5, date = ?6, category = ?7 WHERE id = ?8",
+    let changed = tx.execute_cached(
+        "UPDATE expenses SET type_ = ?1, amount = ?2, or_number = ?3, paid_to = ?4, paid_by = ?5, date = ?6, category = ?7,
+            fund = ?11, appropriation_id = ?9, overspend_override = ?10
+         WHERE id = ?8 AND deleted_at IS NULL",
         params![
             expense.type_,
             expense.amount,
@@ -80,18 +169,27 @@ pub fn update_expense_command(expense: Expense) -> Result<(), String> {
             expense.paid_by,
             expense.date,
             expense.category,
-            expense.id
+            expense.id,
+            expense.appropriation_id,
+            overspend_override,
+            expense.fund.as_str()
         ],
-    ).map_err(|e| e.to_string())?;
+    )?;
+    if changed == 0 {
+        return Err(BmsError::not_found("Expense not found"));
+    }
+
+    record_change(&tx, &user, "expenses", id.into(), Operation::Update, before)?;
+    tx.commit()?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn save_expense_command(expense: Expense) -> Result<(), String> {
+pub fn save_expense_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, expense: Expense) -> Result<(), BmsError> {
     if let Some(_) = expense.id {
-        update_expense_command(expense)
+        update_expense_command(pool, session, token, expense)
     } else {
-        insert_expense_command(expense)
+        insert_expense_command(pool, session, token, expense)
     }
 }
\ No newline at end of file
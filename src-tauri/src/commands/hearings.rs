@@ -0,0 +1,380 @@
+use crate::auth::permissions::{ANY_ROLE, RECORD_EDITORS};
+use crate::auth::session::SessionState;
+use crate::database::audit::{record_change, snapshot, Operation};
+use crate::database::blotter::{parse_column, parse_optional_column, record_hearing, stage_column};
+use crate::database::connection::{DbPool, ExecuteCached};
+use crate::error::BmsError;
+use tauri::State;
+use crate::models::hearing::{check_slot, Hearing, HearingAttendance, HearingReschedule, HearingResult, HearingStatus};
+use crate::validation::{Validate, Validator};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+const HEARING_COLUMNS: &str = "h.id, h.blotter_id, h.stage, h.scheduled_at, h.duration_minutes, h.venue, h.summons_served_at, \
+    h.status, h.minutes, h.outcome, h.rescheduled_from, 'Case #' || b.id || ' - ' || b.type_";
+
+fn hearing_from_row(row: &Row) -> rusqlite::Result<Hearing> {
+    Ok(Hearing {
+        id: row.get(0)?,
+        blotter_id: row.get(1)?,
+        stage: stage_column(row, 2)?,
+        scheduled_at: row.get(3)?,
+        duration_minutes: row.get(4)?,
+        venue: row.get(5)?,
+        summons_served_at: row.get(6)?,
+        status: parse_column(row, 7)?,
+        minutes: row.get(8)?,
+        outcome: row.get(9)?,
+        rescheduled_from: row.get(10)?,
+        attendance: Vec::new(),
+        case_title: row.get(11)?,
+    })
+}
+
+/// Every party to the hearing's case, with the attendance recorded for them.
+fn attendance(conn: &Connection, hearing: &Hearing) -> rusqlite::Result<Vec<HearingAttendance>> {
+    conn.prepare_cached(
+        "SELECT p.id, p.role, p.name, a.attended, COALESCE(a.remarks, '')
+         FROM blotter_parties p
+         LEFT JOIN blotter_hearing_attendance a ON a.party_id = p.id AND a.hearing_id = ?1
+         WHERE p.blotter_id = ?2
+         ORDER BY CASE p.role WHEN 'complainant' THEN 0 WHEN 'respondent' THEN 1 ELSE 2 END, p.id",
+    )?
+    .query_map(params![hearing.id, hearing.blotter_id], |row| {
+        Ok(HearingAttendance {
+            party_id: row.get(0)?,
+            role: parse_optional_column(row, 1)?,
+            name: row.get(2)?,
+            attended: row.get(3)?,
+            remarks: row.get(4)?,
+        })
+    })?
+    .collect()
+}
+
+fn load_hearing(conn: &Connection, hearing_id: i32) -> Result<Hearing, BmsError> {
+    let mut hearing = conn
+        .prepare_cached(&format!(
+            "SELECT {} FROM blotter_hearings h JOIN blotters b ON b.id = h.blotter_id
+             WHERE h.id = ?1 AND b.deleted_at IS NULL",
+            HEARING_COLUMNS
+        ))?
+        .query_row(params![hearing_id], hearing_from_row)
+        .optional()?
+        .ok_or_else(|| BmsError::not_found("Hearing not found"))?;
+    hearing.attendance = attendance(conn, &hearing)?;
+    Ok(hearing)
+}
+
+/// Like `load_hearing`, but only for a hearing that has not yet taken place.
+fn load_scheduled_hearing(conn: &Connection, hearing_id: i32) -> Result<Hearing, BmsError> {
+    let hearing = load_hearing(conn, hearing_id)?;
+    if hearing.status != HearingStatus::Scheduled {
+        return Err(BmsError::conflict(format!(
+            "This hearing has already been {}",
+            hearing.status.as_str()
+        )));
+    }
+    Ok(hearing)
+}
+
+/// Scheduled hearings at `venue` whose time overlaps the given slot, leaving
+/// out `exclude` (the hearing being moved).
+fn overlapping_hearings(
+    conn: &Connection,
+    scheduled_at: &str,
+    duration_minutes: i32,
+    venue: &str,
+    exclude: Option<i32>,
+) -> rusqlite::Result<Vec<Hearing>> {
+    conn.prepare_cached(&format!(
+        "SELECT {} FROM blotter_hearings h JOIN blotters b ON b.id = h.blotter_id
+         WHERE h.status = 'scheduled' AND b.deleted_at IS NULL
+           AND lower(trim(h.venue)) = lower(trim(?3))
+           AND datetime(h.scheduled_at) < datetime(?1, '+' || ?2 || ' minutes')
+           AND datetime(?1) < datetime(h.scheduled_at, '+' || h.duration_minutes || ' minutes')
+           AND h.id IS NOT ?4
+         ORDER BY h.scheduled_at",
+        HEARING_COLUMNS
+    ))?
+    .query_map(params![scheduled_at, duration_minutes, venue, exclude], hearing_from_row)?
+    .collect()
+}
+
+fn ensure_slot_free(conn: &Connection, hearing: &Hearing, exclude: Option<i32>) -> Result<(), BmsError> {
+    let taken = overlapping_hearings(conn, &hearing.scheduled_at, hearing.duration_minutes, &hearing.venue, exclude)?;
+    match taken.first() {
+        Some(other) => Err(BmsError::conflict(format!(
+            "{} is already booked at {} for {}",
+            other.venue, other.scheduled_at, other.case_title
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Inserts a scheduled hearing for a case that is still open and returns its
+/// id. The slot must already have been checked.
+fn insert_hearing(conn: &Connection, hearing: &Hearing, rescheduled_from: Option<i32>) -> Result<i64, BmsError> {
+    let stage = conn
+        .prepare_cached("SELECT stage FROM blotters WHERE id = ?1 AND deleted_at IS NULL")?
+        .query_row(params![hearing.blotter_id], |row| stage_column(row, 0))
+        .optional()?
+        .ok_or_else(|| BmsError::invalid("blotter_id", "Blotter not found"))?;
+    if stage.is_closed() {
+        return Err(BmsError::conflict(format!(
+            "Hearings cannot be scheduled for a case that is {}",
+            stage.label()
+        )));
+    }
+
+    conn.execute_cached(
+        "INSERT INTO blotter_hearings (blotter_id, stage, scheduled_at, duration_minutes, venue, summons_served_at, rescheduled_from)
+         VALUES (?1, ?2, strftime('%Y-%m-%d %H:%M', ?3), ?4, ?5, NULLIF(?6, ''), ?7)",
+        params![
+            hearing.blotter_id,
+            stage.as_str(),
+            hearing.scheduled_at,
+            hearing.duration_minutes,
+            hearing.venue.trim(),
+            hearing.summons_served_at,
+            rescheduled_from
+        ],
+    )?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+/// Points the blotter's `hearing_date` at its next scheduled hearing, or the
+/// last one held if none is coming up.
+fn refresh_hearing_date(conn: &Connection, blotter_id: i32) -> rusqlite::Result<()> {
+    conn.execute_cached(
+        "UPDATE blotters SET hearing_date = COALESCE(
+            (SELECT MIN(scheduled_at) FROM blotter_hearings WHERE blotter_id = ?1 AND status = 'scheduled'),
+            (SELECT MAX(scheduled_at) FROM blotter_hearings WHERE blotter_id = ?1 AND status = 'held'),
+            ''
+         ) WHERE id = ?1",
+        params![blotter_id],
+    )?;
+    Ok(())
+}
+
+/// Hearings of a case, earliest first, including rescheduled and cancelled ones.
+#[tauri::command]
+pub fn fetch_hearings_by_blotter_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, blotter_id: i32) -> Result<Vec<Hearing>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
+
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {} FROM blotter_hearings h JOIN blotters b ON b.id = h.blotter_id
+         WHERE h.blotter_id = ?1 AND b.deleted_at IS NULL
+         ORDER BY h.scheduled_at, h.id",
+        HEARING_COLUMNS
+    ))?;
+
+    let hearings_iter = stmt.query_map(params![blotter_id], hearing_from_row)?;
+
+    let mut hearings = Vec::new();
+    for hearing in hearings_iter {
+        let mut hearing = hearing?;
+        hearing.attendance = attendance(&conn, &hearing)?;
+        hearings.push(hearing);
+    }
+
+    Ok(hearings)
+}
+
+/// Scheduled and held hearings of every case between two dates, inclusive.
+#[tauri::command]
+pub fn fetch_hearing_calendar_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, date_from: String, date_to: String) -> Result<Vec<Hearing>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    Validator::new()
+        .date("date_from", &date_from)
+        .date("date_to", &date_to)
+        .finish()?;
+    let conn = pool.get()?;
+
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {} FROM blotter_hearings h JOIN blotters b ON b.id = h.blotter_id
+         WHERE h.status IN ('scheduled', 'held') AND b.deleted_at IS NULL
+           AND date(h.scheduled_at) BETWEEN date(?1) AND date(?2)
+         ORDER BY h.scheduled_at, h.id",
+        HEARING_COLUMNS
+    ))?;
+
+    let hearings = stmt
+        .query_map(params![date_from, date_to], hearing_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(hearings)
+}
+
+/// Hearings already booked in a slot, so the form can warn before saving.
+#[tauri::command]
+pub fn check_hearing_slot_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, scheduled_at: String, duration_minutes: i32, venue: String, exclude_id: Option<i32>) -> Result<Vec<Hearing>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    check_slot(&mut Validator::new(), &scheduled_at, duration_minutes).finish()?;
+    let conn = pool.get()?;
+
+    Ok(overlapping_hearings(&conn, &scheduled_at, duration_minutes, &venue, exclude_id)?)
+}
+
+#[tauri::command]
+pub fn schedule_hearing_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, hearing: Hearing) -> Result<i64, BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    hearing.validate()?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+
+    ensure_slot_free(&tx, &hearing, None)?;
+    let id = insert_hearing(&tx, &hearing, None)?;
+    record_hearing(&tx, &user, hearing.blotter_id.into(), &hearing.scheduled_at, &format!("Scheduled at {}", hearing.venue.trim()))?;
+    refresh_hearing_date(&tx, hearing.blotter_id)?;
+    record_change(&tx, &user, "blotter_hearings", id, Operation::Create, None)?;
+    tx.commit()?;
+
+    Ok(id)
+}
+
+/// Moves a hearing to a new slot. The original is kept, marked rescheduled,
+/// and a new hearing is booked in its place; its id is returned. A new
+/// summons has to be served for the new date.
+#[tauri::command]
+pub fn reschedule_hearing_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, hearing_id: i32, reschedule: HearingReschedule) -> Result<i64, BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    Validator::new().required("reason", &reschedule.reason).finish()?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+    let original = load_scheduled_hearing(&tx, hearing_id)?;
+
+    let hearing = Hearing {
+        id: None,
+        scheduled_at: reschedule.scheduled_at,
+        duration_minutes: reschedule.duration_minutes.unwrap_or(original.duration_minutes),
+        venue: reschedule.venue.unwrap_or_else(|| original.venue.clone()),
+        summons_served_at: None,
+        ..original
+    };
+    hearing.validate()?;
+    ensure_slot_free(&tx, &hearing, Some(hearing_id))?;
+
+    let before = snapshot(&tx, "blotter_hearings", hearing_id.into())?;
+    tx.execute_cached(
+        "UPDATE blotter_hearings SET status = ?1, outcome = ?2 WHERE id = ?3",
+        params![HearingStatus::Rescheduled.as_str(), reschedule.reason.trim(), hearing_id],
+    )?;
+    record_change(&tx, &user, "blotter_hearings", hearing_id.into(), Operation::Update, before)?;
+
+    let id = insert_hearing(&tx, &hearing, Some(hearing_id))?;
+    record_hearing(&tx, &user, hearing.blotter_id.into(), &hearing.scheduled_at, &format!("Rescheduled: {}", reschedule.reason.trim()))?;
+    refresh_hearing_date(&tx, hearing.blotter_id)?;
+    record_change(&tx, &user, "blotter_hearings", id, Operation::Create, None)?;
+    tx.commit()?;
+
+    Ok(id)
+}
+
+#[tauri::command]
+pub fn cancel_hearing_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, hearing_id: i32, reason: String) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    Validator::new().required("reason", &reason).finish()?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+    let hearing = load_scheduled_hearing(&tx, hearing_id)?;
+    let before = snapshot(&tx, "blotter_hearings", hearing_id.into())?;
+
+    tx.execute_cached(
+        "UPDATE blotter_hearings SET status = ?1, outcome = ?2 WHERE id = ?3",
+        params![HearingStatus::Cancelled.as_str(), reason.trim(), hearing_id],
+    )?;
+
+    record_hearing(&tx, &user, hearing.blotter_id.into(), &hearing.scheduled_at, &format!("Cancelled: {}", reason.trim()))?;
+    refresh_hearing_date(&tx, hearing.blotter_id)?;
+    record_change(&tx, &user, "blotter_hearings", hearing_id.into(), Operation::Update, before)?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn record_summons_served_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, hearing_id: i32, served_at: String) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    Validator::new().date("served_at", &served_at).finish()?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+    load_scheduled_hearing(&tx, hearing_id)?;
+    let before = snapshot(&tx, "blotter_hearings", hearing_id.into())?;
+
+    tx.execute_cached(
+        "UPDATE blotter_hearings SET summons_served_at = date(?1) WHERE id = ?2",
+        params![served_at, hearing_id],
+    )?;
+
+    record_change(&tx, &user, "blotter_hearings", hearing_id.into(), Operation::Update, before)?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+/// Marks a hearing held and records who came, the minutes and the outcome.
+/// May be called again on a held hearing to correct the record, but not
+/// before the hearing's scheduled time.
+#[tauri::command]
+pub fn record_hearing_result_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, hearing_id: i32, result: HearingResult) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    result.validate()?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+    let hearing = load_hearing(&tx, hearing_id)?;
+    if !matches!(hearing.status, HearingStatus::Scheduled | HearingStatus::Held) {
+        return Err(BmsError::conflict(format!(
+            "This hearing has already been {}",
+            hearing.status.as_str()
+        )));
+    }
+    let upcoming: bool = tx
+        .prepare_cached("SELECT datetime(?1) > datetime('now', 'localtime')")?
+        .query_row(params![hearing.scheduled_at], |row| row.get(0))?;
+    if upcoming {
+        return Err(BmsError::conflict(format!(
+            "This hearing is not until {}; its result can be recorded once it has taken place",
+            hearing.scheduled_at
+        )));
+    }
+    if result
+        .attendance
+        .iter()
+        .any(|a| !hearing.attendance.iter().any(|p| p.party_id == a.party_id))
+    {
+        return Err(BmsError::invalid("attendance", "Attendance lists someone who is not a party to this case"));
+    }
+    let before = snapshot(&tx, "blotter_hearings", hearing_id.into())?;
+
+    tx.execute_cached(
+        "DELETE FROM blotter_hearing_attendance WHERE hearing_id = ?1",
+        params![hearing_id],
+    )?;
+    for entry in &result.attendance {
+        tx.execute_cached(
+            "INSERT INTO blotter_hearing_attendance (hearing_id, party_id, attended, remarks)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![hearing_id, entry.party_id, entry.attended, entry.remarks],
+        )?;
+    }
+    tx.execute_cached(
+        "UPDATE blotter_hearings SET status = ?1, minutes = ?2, outcome = ?3 WHERE id = ?4",
+        params![HearingStatus::Held.as_str(), result.minutes, result.outcome, hearing_id],
+    )?;
+
+    if hearing.status == HearingStatus::Scheduled {
+        let no_shows = result.attendance.iter().filter(|a| a.attended == Some(false)).count();
+        let notes = match no_shows {
+            0 => format!("Held: {}", result.outcome.trim()),
+            n => format!("Held with {} absent: {}", n, result.outcome.trim()),
+        };
+        record_hearing(&tx, &user, hearing.blotter_id.into(), &hearing.scheduled_at, &notes)?;
+    }
+    refresh_hearing_date(&tx, hearing.blotter_id)?;
+    record_change(&tx, &user, "blotter_hearings", hearing_id.into(), Operation::Update, before)?;
+    tx.commit()?;
+
+    Ok(())
+}
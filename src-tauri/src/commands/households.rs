@@ -1,130 +1,220 @@
+use rusqlite::{params, Connection};
+use crate::auth::permissions::{ANY_ROLE, RECORD_EDITORS};
+use crate::auth::session::SessionState;
+use crate::database::audit::{record_change, snapshot, Operation};
+use crate::database::connection::{DbPool, ExecuteCached};
+use crate::database::household::{members, replace_members, RESIDENT_NAME_SQL};
+use crate::database::recycle_bin::{restore, soft_delete};
+use crate::error::BmsError;
+use tauri::State;
+use crate::models::household::{Household, HouseholdMember, UnmatchedHouseholdMember};
+use crate::validation::Validate;
+
 #[tauri::command]
-pub fn fetch_members_by_household_command(household_id: i32) -> Result<Vec<String>, String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn fetch_members_by_household_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, household_id: i32) -> Result<Vec<HouseholdMember>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
 
-    let mut stmt = conn
-        .prepare("SELECT selected_residents FROM households WHERE id = ?1")
-        .map_err(|e| e.to_string())?;
+    Ok(members(&conn, household_id.into())?)
+}
 
-    let selected_residents_json: String = stmt
-        .query_row(params![household_id], |row| row.get(0))
-        .map_err(|e| e.to_string())?;
+/// Names left over from before members were linked to residents, for a clerk
+/// to re-enter by picking the right resident. Saving the household clears
+/// its entries.
+#[tauri::command]
+pub fn fetch_unmatched_household_members_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String) -> Result<Vec<UnmatchedHouseholdMember>, BmsError> {
+    session.require(&token, RECORD_EDITORS)?;
+    let conn = pool.get()?;
+
+    let mut stmt = conn.prepare_cached(
+        "SELECT i.id, i.household_id, h.household_number, i.name, i.is_head, i.reason
+         FROM household_member_import_issues i JOIN households h ON h.id = i.household_id
+         WHERE h.deleted_at IS NULL
+         ORDER BY h.household_number, i.is_head DESC, i.name",
+    )?;
+
+    let unmatched_iter = stmt.query_map([], |row| {
+        Ok(UnmatchedHouseholdMember {
+            id: row.get(0)?,
+            household_id: row.get(1)?,
+            household_number: row.get(2)?,
+            name: row.get(3)?,
+            is_head: row.get(4)?,
+            reason: row.get(5)?,
+        })
+    })?;
 
-    let selected_residents: Vec<String> =
-        serde_json::from_str(&selected_residents_json).map_err(|e| e.to_string())?;
+    let mut unmatched = Vec::new();
+    for entry in unmatched_iter {
+        unmatched.push(entry?);
+    }
 
-    Ok(selected_residents)
+    Ok(unmatched)
+}
+
+/// Stores the member list of a household after checking that every
+/// resident it names exists.
+fn save_members(conn: &Connection, household_id: i64, household: &Household) -> Result<(), BmsError> {
+    let ids = household
+        .residents
+        .iter()
+        .map(|m| m.resident_id)
+        .chain(household.head_resident_id);
+    for resident_id in ids {
+        let exists: bool = conn
+            .prepare_cached("SELECT EXISTS (SELECT 1 FROM residents WHERE id = ?1 AND deleted_at IS NULL)")?
+            .query_row(params![resident_id], |row| row.get(0))?;
+        if !exists {
+            return Err(BmsError::invalid("residents", "One of the selected residents no longer exists"));
+        }
+    }
+
+    replace_members(conn, household_id, household.head_resident_id, &household.residents)?;
+    conn.execute_cached(
+        "DELETE FROM household_member_import_issues WHERE household_id = ?1",
+        params![household_id],
+    )?;
+
+    Ok(())
 }
-use rusqlite::params;
-use crate::database::connection::establish_connection;
-use crate::models::household::Household;
 
 #[tauri::command]
-pub fn fetch_all_households_command() -> Result<Vec<Household>, String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn fetch_all_households_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String) -> Result<Vec<Household>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
 
-    let mut stmt = conn.prepare(
-        "SELECT id, household_number, type_, members, head, zone, date, status, selected_residents FROM households"
-    ).map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT h.id, h.household_number, h.type_, h.head_resident_id, COALESCE({}, ''), h.zone, h.date, h.status
+         FROM households h LEFT JOIN residents r ON r.id = h.head_resident_id AND r.deleted_at IS NULL
+         WHERE h.deleted_at IS NULL",
+        RESIDENT_NAME_SQL
+    ))?;
 
     let household_iter = stmt
         .query_map([], |row| {
-            let residents_json: String = row.get(8)?;
-            let selected_residents: Vec<String> = serde_json::from_str(&residents_json).unwrap_or_default();
-
             Ok(Household {
                 id: row.get(0)?,
                 household_number: row.get(1)?,
                 type_: row.get(2)?,
-                members: row.get(3)?,
+                members: 0,
+                head_resident_id: row.get(3)?,
                 head: row.get(4)?,
                 zone: row.get(5)?,
                 date: row.get(6)?,
                 status: row.get(7)?,
-                selected_residents,
+                residents: Vec::new(),
             })
-        })
-        .map_err(|e| e.to_string())?;
+        })?;
 
     let mut households = Vec::new();
     for household in household_iter {
-        households.push(household.map_err(|e| e.to_string())?);
+        let mut household = household?;
+        if let Some(id) = household.id {
+            household.residents = members(&conn, id.into())?;
+            household.members = household.residents.len() as i32;
+        }
+        households.push(household);
     }
 
     Ok(households)
 }
 
 #[tauri::command]
-pub fn insert_household_command(household: Household) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
-    let residents_json = serde_json::to_string(&household.selected_residents).map_err(|e| e.to_string())?;
+pub fn insert_household_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, household: Household) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    household.validate()?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
 
-    conn.execute(
+    tx.execute_cached(
         "INSERT INTO households (
-            household_number, type_, members, head, zone, date, status, selected_residents
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            household_number, type_, head_resident_id, zone, date, status
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
         params![
             household.household_number,
             household.type_,
-            household.members,
-            household.head,
-            household.zone,
+            household.head_resident_id,
+            household.zone.trim(),
             household.date,
             household.status,
-            residents_json,
         ],
-    ).map_err(|e| e.to_string())?;
+    )?;
+
+    let id = tx.last_insert_rowid();
+    save_members(&tx, id, &household)?;
+    record_change(&tx, &user, "households", id, Operation::Create, None)?;
+    tx.commit()?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn update_household_command(household: Household) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
-    let residents_json = serde_json::to_string(&household.selected_residents).map_err(|e| e.to_string())?;
-
-    conn.execute(
+pub fn update_household_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, household: Household) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    household.validate()?;
+    let id = household.id.ok_or_else(|| BmsError::invalid("id", "Household id is required"))?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+    let before = snapshot(&tx, "households", id.into())?;
+
+    let changed = tx.execute_cached(
         "UPDATE households SET
             household_number = ?1,
             type_ = ?2,
-            members = ?3,
-            head = ?4,
-            zone = ?5,
-            date = ?6,
-            status = ?7,
-            selected_residents = ?8
-         WHERE id = ?9",
+            head_resident_id = ?3,
+            zone = ?4,
+            date = ?5,
+            status = ?6
+         WHERE id = ?7 AND deleted_at IS NULL",
         params![
             household.household_number,
             household.type_,
-            household.members,
-            household.head,
-            household.zone,
+            household.head_resident_id,
+            household.zone.trim(),
             household.date,
             household.status,
-            residents_json,
             household.id
         ],
-    ).map_err(|e| e.to_string())?;
+    )?;
+    if changed == 0 {
+        return Err(BmsError::not_found("Household not found"));
+    }
+
+    save_members(&tx, id.into(), &household)?;
+
+    record_change(&tx, &user, "households", id.into(), Operation::Update, before)?;
+    tx.commit()?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn save_household_command(household: Household) -> Result<(), String> {
+pub fn save_household_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, household: Household) -> Result<(), BmsError> {
     if household.id.is_some() {
-        update_household_command(household)
+        update_household_command(pool, session, token, household)
     } else {
-        insert_household_command(household)
+        insert_household_command(pool, session, token, household)
     }
 }
 
 #[tauri::command]
-pub fn delete_household_command(id: i32) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn delete_household_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    let conn = pool.get()?;
+
+    soft_delete(&conn, &user, "households", id.into())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn restore_household_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    let conn = pool.get()?;
 
-    conn.execute("DELETE FROM households WHERE id = ?", params![id])
-        .map_err(|e| e.to_string())?;
+    if !restore(&conn, &user, "households", id.into())? {
+        return Err(BmsError::not_found("Household is not in the recycle bin"));
+    }
 
     Ok(())
 }
\ No newline at end of file
@@ -1,79 +1,184 @@
-use crate::database::connection::establish_connection;
-use rusqlite::params;
-use crate::models::income::Income;
+use crate::auth::permissions::{ANY_ROLE, TREASURY};
+use crate::auth::session::SessionState;
+use crate::database::audit::{record_change, snapshot, Operation};
+use crate::database::blotter::parse_column;
+use crate::database::cashbook::{ensure_open, ensure_row_open};
+use crate::database::connection::{DbPool, ExecuteCached};
+use crate::database::income::{
+    booklet_of, booklets_registered, insert_income, next_or_number, or_number_taken, void_income, ReceiptNumber,
+};
+use crate::database::query::{fetch_page, Filters, Listing};
+use crate::database::recycle_bin::{restore, soft_delete};
+use crate::error::BmsError;
+use tauri::State;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use crate::models::income::{Income, IncomeFilter};
+use crate::models::query::{Page, PageRequest};
+use crate::models::user::SessionUser;
+use crate::validation::{Validate, Validator};
 
 #[tauri::command]
-pub fn delete_income_command(id: i32) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn delete_income_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<(), BmsError> {
+    let user = session.require(&token, TREASURY)?;
+    let conn = pool.get()?;
+    ensure_row_open(&conn, "incomes", id.into())?;
 
-    conn.execute(
-        "DELETE FROM incomes WHERE id = ?1",
-        params![id],
-    ).map_err(|e| e.to_string())?;
+    soft_delete(&conn, &user, "incomes", id.into())?;
 
     Ok(())
 }
 
+#[tauri::command]
+pub fn restore_income_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<(), BmsError> {
+    let user = session.require(&token, TREASURY)?;
+    let conn = pool.get()?;
+    ensure_row_open(&conn, "incomes", id.into())?;
+
+    if !restore(&conn, &user, "incomes", id.into())? {
+        return Err(BmsError::not_found("Income is not in the recycle bin"));
+    }
+
+    Ok(())
+}
+
+
+const INCOME_COLUMNS: &str = "id, type_, amount, or_number, received_from, received_by, category, date, voided_at, void_reason, fund";
+
+const INCOME_LISTING: Listing = Listing {
+    table: "incomes",
+    columns: INCOME_COLUMNS,
+    sortable: &["date", "amount", "or_number", "category", "type_", "received_from"],
+    default_sort: "date",
+};
+
+fn income_from_row(row: &Row) -> rusqlite::Result<Income> {
+    Ok(Income {
+        id: row.get(0)?,
+        type_: row.get(1)?,
+        amount: row.get(2)?,
+        or_number: row.get(3)?,
+        received_from: row.get(4)?,
+        received_by: row.get(5)?,
+        category: row.get(6)?,
+        date: row.get(7)?,
+        voided_at: row.get(8)?,
+        void_reason: row.get(9)?,
+        fund: parse_column(row, 10)?,
+    })
+}
 
 #[tauri::command]
-pub fn fetch_all_incomes_command() -> Result<Vec<Income>, String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn fetch_all_incomes_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String) -> Result<Vec<Income>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
 
     let mut stmt = conn
-        .prepare("SELECT id, type_, amount, or_number, received_from, received_by, category, date FROM incomes")
-        .map_err(|e| e.to_string())?;
+        .prepare_cached(&format!("SELECT {} FROM incomes WHERE deleted_at IS NULL", INCOME_COLUMNS))?;
 
     let income_iter = stmt
-        .query_map([], |row| {
-            Ok(Income {
-                id: row.get(0)?,
-                type_: row.get(1)?,
-                amount: row.get(2)?,
-                or_number: row.get(3)?,
-                received_from: row.get(4)?,
-                received_by: row.get(5)?,
-                category: row.get(6)?,
-                date: row.get(7)?,
-            })
-        })
-        .map_err(|e| e.to_string())?;
+        .query_map([], income_from_row)?;
 
     let mut incomes = Vec::new();
     for income in income_iter {
-        incomes.push(income.map_err(|e| e.to_string())?);
+        incomes.push(income?);
     }
 
     Ok(incomes)
 }
 
 #[tauri::command]
-pub fn insert_income_command(income: Income) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn query_incomes_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, page: PageRequest, filter: Option<IncomeFilter>) -> Result<Page<Income>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
+    let filter = filter.unwrap_or_default();
+
+    let mut filters = Filters::new();
+    filters
+        .raw("deleted_at IS NULL")
+        .opt("type_ = ?", filter.type_)
+        .opt("category = ?", filter.category)
+        .opt("date(date) >= date(?)", filter.date_from)
+        .opt("date(date) <= date(?)", filter.date_to);
+
+    fetch_page(&conn, &INCOME_LISTING, &filters, &page, income_from_row)
+}
 
-    conn.execute(
-        "INSERT INTO incomes (type_, amount, or_number, received_from, received_by, category, date)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-        params![
-            income.type_,
-            income.amount,
-            income.or_number,
-            income.received_from,
-            income.received_by,
-            income.category,
-            income.date,
-        ],
-    ).map_err(|e| e.to_string())?;
+/// The receipt an income is recorded under: the next one from the
+/// collector's booklet when `or_number` is 0, otherwise the number given,
+/// which has to be unused and, once booklets are registered, in one of them.
+fn receipt_for(conn: &Connection, user: &SessionUser, or_number: i32, income_id: Option<i64>) -> Result<ReceiptNumber, BmsError> {
+    if or_number == 0 {
+        return next_or_number(conn, user.id)?
+            .ok_or_else(|| BmsError::conflict("No OR booklet with unused receipts is assigned to you"));
+    }
 
-    Ok(())
+    if or_number_taken(conn, or_number, income_id)? {
+        return Err(BmsError::conflict(format!("OR number {} has already been used or cancelled", or_number)));
+    }
+    let booklet_id = booklet_of(conn, or_number)?;
+    if booklet_id.is_none() && booklets_registered(conn)? {
+        return Err(BmsError::invalid(
+            "or_number",
+            format!("OR number {} is not in any registered booklet", or_number),
+        ));
+    }
+
+    Ok(ReceiptNumber { or_number, booklet_id })
 }
 
+/// Records an income and returns its receipt number.
 #[tauri::command]
-pub fn update_income_command(income: Income) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn insert_income_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, mut income: Income) -> Result<i32, BmsError> {
+    let user = session.require(&token, TREASURY)?;
+    income.validate()?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+    ensure_open(&tx, &income.date)?;
+
+    let receipt = receipt_for(&tx, &user, income.or_number, None)?;
+    income.or_number = receipt.or_number;
+    let id = insert_income(&tx, &income, receipt.booklet_id)?;
+    record_change(&tx, &user, "incomes", id, Operation::Create, None)?;
+    tx.commit()?;
+
+    Ok(receipt.or_number)
+}
 
-    conn.execute(
-        "UPDATE incomes SET type_ = ?1, amount = ?2, or_number = ?3, received_from = ?4, received_by = ?5, date = ?6, category = ?7 WHERE id = ?8",
-        rusqlite::params![
+#[tauri::command]
+pub fn update_income_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, income: Income) -> Result<(), BmsError> {
+    let user = session.require(&token, TREASURY)?;
+    income.validate()?;
+    let id = income.id.ok_or_else(|| BmsError::invalid("id", "Income id is required"))?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+    let before = snapshot(&tx, "incomes", id.into())?;
+    // Checked up front so a missing record is not reported as some other
+    // problem with it.
+    if before.as_ref().is_none_or(|b| !b["deleted_at"].is_null()) {
+        return Err(BmsError::not_found("Income not found"));
+    }
+    if before.as_ref().and_then(|b| b.get("voided_at")).is_some_and(|v| !v.is_null()) {
+        return Err(BmsError::conflict("A voided receipt cannot be edited"));
+    }
+    ensure_row_open(&tx, "incomes", id.into())?;
+    ensure_open(&tx, &income.date)?;
+
+    // A corrected receipt number is checked like a new one, and clears the
+    // duplicate mark of incomes recorded before numbers were checked.
+    let current = before.as_ref().and_then(|b| b.get("or_number")).and_then(|v| v.as_i64());
+    let booklet_id = if current != Some(income.or_number.into()) {
+        receipt_for(&tx, &user, income.or_number, Some(id.into()))?.booklet_id
+    } else {
+        None
+    };
+
+    let changed = tx.execute_cached(
+        "UPDATE incomes SET type_ = ?1, amount = ?2, received_from = ?4, received_by = ?5, date = ?6, category = ?7, fund = ?10,
+            booklet_id = CASE WHEN or_number = ?3 THEN booklet_id ELSE ?9 END,
+            duplicate_of = CASE WHEN or_number = ?3 THEN duplicate_of END,
+            or_number = ?3
+         WHERE id = ?8 AND deleted_at IS NULL",
+        params![
             income.type_,
             income.amount,
             income.or_number,
@@ -81,18 +186,65 @@ pub fn update_income_command(income: Income) -> Result<(), String> {
             income.received_by,
             income.date,
             income.category,
-            income.id
+            income.id,
+            booklet_id,
+            income.fund.as_str()
         ],
-    ).map_err(|e| e.to_string())?;
+    )?;
+    if changed == 0 {
+        return Err(BmsError::not_found("Income not found"));
+    }
+
+    record_change(&tx, &user, "incomes", id.into(), Operation::Update, before)?;
+    tx.commit()?;
 
     Ok(())
 }
 
+/// Returns the receipt number, which is assigned when a new income is
+/// saved without one.
 #[tauri::command]
-pub fn save_income_command(income: Income) -> Result<(), String> {
+pub fn save_income_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, income: Income) -> Result<i32, BmsError> {
     if let Some(_) = income.id {
-        update_income_command(income)
+        let or_number = income.or_number;
+        update_income_command(pool, session, token, income)?;
+        Ok(or_number)
     } else {
-        insert_income_command(income)
+        insert_income_command(pool, session, token, income)
     }
-}
\ No newline at end of file
+}
+
+/// Voids a receipt issued in error. It keeps its number, with the reason,
+/// in the receipt report. Receipts for certificate fees are voided by
+/// voiding the certificate.
+#[tauri::command]
+pub fn void_income_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32, reason: String) -> Result<(), BmsError> {
+    let user = session.require(&token, TREASURY)?;
+    Validator::new().required("reason", &reason).finish()?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+
+    let certificate: Option<Option<String>> = tx
+        .prepare_cached("SELECT control_number FROM certificates WHERE income_id = ?1 AND voided_at IS NULL")?
+        .query_row(params![id], |row| row.get(0))
+        .optional()?;
+    if let Some(control_number) = certificate {
+        return Err(BmsError::conflict(format!(
+            "This receipt is for certificate {}; void the certificate instead",
+            control_number.unwrap_or_default()
+        )));
+    }
+
+    let before = snapshot(&tx, "incomes", id.into())?;
+    if before.is_none() {
+        return Err(BmsError::not_found("Income not found"));
+    }
+    ensure_row_open(&tx, "incomes", id.into())?;
+    if !void_income(&tx, id.into(), reason.trim())? {
+        return Err(BmsError::conflict("This receipt has already been voided"));
+    }
+    record_change(&tx, &user, "incomes", id.into(), Operation::Update, before)?;
+    tx.commit()?;
+
+    Ok(())
+}
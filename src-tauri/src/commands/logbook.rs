@@ -1,47 +1,87 @@
-use rusqlite::params;
-use crate::database::connection::establish_connection;
-use crate::models::logbook::Logbook;
+use rusqlite::{params, Row};
+use crate::auth::permissions::{ANY_ROLE, RECORD_EDITORS};
+use crate::auth::session::SessionState;
+use crate::database::audit::{record_change, snapshot, Operation};
+use crate::database::connection::{DbPool, ExecuteCached};
+use crate::database::query::{fetch_page, Filters, Listing};
+use crate::database::recycle_bin::{restore, soft_delete};
+use crate::error::BmsError;
+use tauri::State;
+use crate::models::logbook::{Logbook, LogbookFilter};
+use crate::models::query::{Page, PageRequest};
+use crate::validation::Validate;
+
+const LOGBOOK_COLUMNS: &str = "id, official_name, date, time_in_am, time_out_am, time_in_pm, time_out_pm, remarks, status, total_hours, created_at, updated_at";
+
+const LOGBOOK_LISTING: Listing = Listing {
+    table: "logbook",
+    columns: LOGBOOK_COLUMNS,
+    sortable: &["date", "official_name", "status", "total_hours"],
+    default_sort: "date",
+};
+
+fn logbook_entry_from_row(row: &Row) -> rusqlite::Result<Logbook> {
+    Ok(Logbook {
+        id: row.get(0)?,
+        official_name: row.get(1)?,
+        date: row.get(2)?,
+        time_in_am: row.get(3)?,
+        time_out_am: row.get(4)?,
+        time_in_pm: row.get(5)?,
+        time_out_pm: row.get(6)?,
+        remarks: row.get(7)?,
+        status: row.get(8)?,
+        total_hours: row.get(9)?,
+        created_at: row.get(10)?,
+        updated_at: row.get(11)?,
+    })
+}
 
 #[tauri::command]
-pub fn fetch_all_logbook_entries_command() -> Result<Vec<Logbook>, String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn fetch_all_logbook_entries_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String) -> Result<Vec<Logbook>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
 
-    let mut stmt = conn.prepare(
-        "SELECT id, official_name, date, time_in_am, time_out_am, time_in_pm, time_out_pm, remarks, status, total_hours, created_at, updated_at FROM logbook"
-    ).map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare_cached(
+        &format!("SELECT {} FROM logbook WHERE deleted_at IS NULL", LOGBOOK_COLUMNS)
+    )?;
 
     let rows = stmt
-        .query_map([], |row| {
-            Ok(Logbook {
-                id: row.get(0)?,
-                official_name: row.get(1)?,
-                date: row.get(2)?,
-                time_in_am: row.get(3)?,
-                time_out_am: row.get(4)?,
-                time_in_pm: row.get(5)?,
-                time_out_pm: row.get(6)?,
-                remarks: row.get(7)?,
-                status: row.get(8)?,
-                total_hours: row.get(9)?,
-                created_at: row.get(10)?,
-                updated_at: row.get(11)?,
-            })
-        })
-        .map_err(|e| e.to_string())?;
+        .query_map([], logbook_entry_from_row)?;
 
     let mut entries = Vec::new();
     for entry in rows {
-        entries.push(entry.map_err(|e| e.to_string())?);
+        entries.push(entry?);
     }
 
     Ok(entries)
 }
 
 #[tauri::command]
-pub fn insert_logbook_entry_command(entry: Logbook) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn query_logbook_entries_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, page: PageRequest, filter: Option<LogbookFilter>) -> Result<Page<Logbook>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
+    let filter = filter.unwrap_or_default();
+
+    let mut filters = Filters::new();
+    filters
+        .raw("deleted_at IS NULL")
+        .opt("official_name = ?", filter.official_name)
+        .opt("status = ?", filter.status)
+        .opt("date(date) >= date(?)", filter.date_from)
+        .opt("date(date) <= date(?)", filter.date_to);
+
+    fetch_page(&conn, &LOGBOOK_LISTING, &filters, &page, logbook_entry_from_row)
+}
+
+#[tauri::command]
+pub fn insert_logbook_entry_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, entry: Logbook) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    entry.validate()?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
 
-    conn.execute(
+    tx.execute_cached(
         "INSERT INTO logbook (
             official_name,
             date,
@@ -64,16 +104,24 @@ pub fn insert_logbook_entry_command(entry: Logbook) -> Result<(), String> {
             entry.status,
             entry.total_hours
         ],
-    ).map_err(|e| e.to_string())?;
+    )?;
+
+    record_change(&tx, &user, "logbook", tx.last_insert_rowid(), Operation::Create, None)?;
+    tx.commit()?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn update_logbook_entry_command(entry: Logbook) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn update_logbook_entry_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, entry: Logbook) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    entry.validate()?;
+    let id = entry.id.ok_or_else(|| BmsError::invalid("id", "Logbook entry id is required"))?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+    let before = snapshot(&tx, "logbook", id.into())?;
 
-    conn.execute(
+    let changed = tx.execute_cached(
         "UPDATE logbook SET
             official_name = ?1,
             date = ?2,
@@ -84,7 +132,7 @@ pub fn update_logbook_entry_command(entry: Logbook) -> Result<(), String> {
             remarks = ?7,
             status = ?8,
             total_hours = ?9
-         WHERE id = ?10",
+         WHERE id = ?10 AND deleted_at IS NULL",
         params![
             entry.official_name,
             entry.date,
@@ -97,27 +145,45 @@ pub fn update_logbook_entry_command(entry: Logbook) -> Result<(), String> {
             entry.total_hours,
             entry.id
         ],
-    ).map_err(|e| e.to_string())?;
+    )?;
+    if changed == 0 {
+        return Err(BmsError::not_found("Logbook entry not found"));
+    }
+
+    record_change(&tx, &user, "logbook", id.into(), Operation::Update, before)?;
+    tx.commit()?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn save_logbook_entry_command(entry: Logbook) -> Result<(), String> {
+pub fn save_logbook_entry_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, entry: Logbook) -> Result<(), BmsError> {
     if let Some(id) = entry.id {
         if id > 0 {
-            return update_logbook_entry_command(entry);
+            return update_logbook_entry_command(pool, session, token, entry);
         }
     }
-    insert_logbook_entry_command(entry)
+    insert_logbook_entry_command(pool, session, token, entry)
 }
 
 #[tauri::command]
-pub fn delete_logbook_entry_command(id: i32) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn delete_logbook_entry_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    let conn = pool.get()?;
+
+    soft_delete(&conn, &user, "logbook", id.into())?;
 
-    conn.execute("DELETE FROM logbook WHERE id = ?", params![id])
-        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn restore_logbook_entry_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    let conn = pool.get()?;
+
+    if !restore(&conn, &user, "logbook", id.into())? {
+        return Err(BmsError::not_found("Logbook entry is not in the recycle bin"));
+    }
 
     Ok(())
 }
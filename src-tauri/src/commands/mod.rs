@@ -1,6 +1,11 @@
 pub mod blotters;
+pub mod hearings;
 pub mod income;
+pub mod receipts;
 pub mod expense;
+pub mod budget;
+pub mod reports;
+pub mod cashbook;
 pub mod households;
 pub mod events;
 pub mod settings;
@@ -8,4 +13,7 @@ pub mod residents;
 pub mod officials;
 pub mod certificates;
 pub mod user;
-pub mod logbook;
\ No newline at end of file
+pub mod logbook;
+pub mod audit;
+pub mod recycle_bin;
+pub mod search;
@@ -1,17 +1,24 @@
-use crate::database::connection::establish_connection;
+use crate::auth::permissions::{ADMINISTRATORS, ANY_ROLE};
+use crate::auth::session::SessionState;
+use crate::database::audit::{record_change, snapshot, Operation};
+use crate::database::connection::{DbPool, ExecuteCached};
+use crate::database::recycle_bin::{restore, soft_delete};
+use crate::error::BmsError;
+use tauri::State;
 use crate::models::official::Official;
+use crate::validation::Validate;
 use rusqlite::params;
 
 #[tauri::command]
-pub fn fetch_all_officials_command() -> Result<Vec<Official>, String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn fetch_all_officials_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String) -> Result<Vec<Official>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
 
     let mut stmt = conn
-        .prepare(
+        .prepare_cached(
             "SELECT id, name, role, image, section, age, contact, term_start, term_end, zone 
-             FROM officials",
-        )
-        .map_err(|e| e.to_string())?;
+             FROM officials WHERE deleted_at IS NULL",
+        )?;
 
     let official_iter = stmt
         .query_map([], |row| {
@@ -27,22 +34,24 @@ pub fn fetch_all_officials_command() -> Result<Vec<Official>, String> {
                 term_end: row.get(8)?,
                 zone: row.get(9)?,
             })
-        })
-        .map_err(|e| e.to_string())?;
+        })?;
 
     let mut officials = Vec::new();
     for official in official_iter {
-        officials.push(official.map_err(|e| e.to_string())?);
+        officials.push(official?);
     }
 
     Ok(officials)
 }
 
 #[tauri::command]
-pub fn insert_official_command(official: Official) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn insert_official_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, official: Official) -> Result<(), BmsError> {
+    let user = session.require(&token, ADMINISTRATORS)?;
+    official.validate()?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
 
-    conn.execute(
+    tx.execute_cached(
         "INSERT INTO officials (name, role, image, section, age, contact, term_start, term_end, zone)
          VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
         params![
@@ -56,17 +65,25 @@ pub fn insert_official_command(official: Official) -> Result<(), String> {
             official.term_end,
             official.zone
         ],
-    ).map_err(|e| e.to_string())?;
+    )?;
+
+    record_change(&tx, &user, "officials", tx.last_insert_rowid(), Operation::Create, None)?;
+    tx.commit()?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn save_official_command(official: Official) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn save_official_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, official: Official) -> Result<(), BmsError> {
+    let user = session.require(&token, ADMINISTRATORS)?;
+    official.validate()?;
+    let id = official.id.ok_or_else(|| BmsError::invalid("id", "Official id is required"))?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+    let before = snapshot(&tx, "officials", id.into())?;
 
-    conn.execute(
-        "UPDATE officials SET name = ?1, role = ?2, image = ?3, section = ?4, age = ?5, contact = ?6, term_start = ?7, term_end = ?8, zone = ?9 WHERE id = ?10",
+    let changed = tx.execute_cached(
+        "UPDATE officials SET name = ?1, role = ?2, image = ?3, section = ?4, age = ?5, contact = ?6, term_start = ?7, term_end = ?8, zone = ?9 WHERE id = ?10 AND deleted_at IS NULL",
         params![
             official.name,
             official.role,
@@ -79,17 +96,35 @@ pub fn save_official_command(official: Official) -> Result<(), String> {
             official.zone,
             official.id,
         ],
-    ).map_err(|e| e.to_string())?;
+    )?;
+    if changed == 0 {
+        return Err(BmsError::not_found("Official not found"));
+    }
+
+    record_change(&tx, &user, "officials", id.into(), Operation::Update, before)?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_official_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<(), BmsError> {
+    let user = session.require(&token, ADMINISTRATORS)?;
+    let conn = pool.get()?;
+
+    soft_delete(&conn, &user, "officials", id.into())?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn delete_official_command(id: i32) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn restore_official_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<(), BmsError> {
+    let user = session.require(&token, ADMINISTRATORS)?;
+    let conn = pool.get()?;
 
-    conn.execute("DELETE FROM officials WHERE id = ?1", params![id])
-        .map_err(|e| e.to_string())?;
+    if !restore(&conn, &user, "officials", id.into())? {
+        return Err(BmsError::not_found("Official is not in the recycle bin"));
+    }
 
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,356 @@
+use crate::auth::permissions::{ANY_ROLE, TREASURY};
+use crate::auth::session::SessionState;
+use crate::database::audit::{record_change, snapshot, Operation};
+use crate::database::connection::{DbPool, ExecuteCached};
+use crate::database::income::{booklet_of, or_number_taken, BOOKLET_USAGE};
+use crate::error::BmsError;
+use crate::models::income::{BookletReport, CancelledReceipt, OrBooklet, ReceiptRange, VoidedReceipt};
+use crate::validation::{Validate, Validator};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use tauri::State;
+
+fn booklet_from_row(row: &Row) -> rusqlite::Result<OrBooklet> {
+    let series_end: i32 = row.get(2)?;
+    let last_used: Option<i32> = row.get(7)?;
+    let next_number = match last_used {
+        Some(last) if last >= series_end => None,
+        Some(last) => Some(last + 1),
+        None => Some(row.get(1)?),
+    };
+
+    Ok(OrBooklet {
+        id: row.get(0)?,
+        series_start: row.get(1)?,
+        series_end,
+        collector_id: row.get(3)?,
+        collector: row.get(4)?,
+        registered_at: row.get(5)?,
+        used: row.get(6)?,
+        next_number,
+    })
+}
+
+fn load_booklets(conn: &Connection, booklet_id: Option<i32>) -> rusqlite::Result<Vec<OrBooklet>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "{}
+        SELECT b.id, b.series_start, b.series_end, b.collector_id, u.username, b.registered_at,
+               COUNT(used.or_number), MAX(used.or_number)
+        FROM or_booklets b
+        LEFT JOIN users u ON u.id = b.collector_id
+        LEFT JOIN used ON used.booklet_id = b.id
+        WHERE ?1 IS NULL OR b.id = ?1
+        GROUP BY b.id
+        ORDER BY b.series_start",
+        BOOKLET_USAGE
+    ))?;
+
+    let booklets = stmt
+        .query_map(params![booklet_id], booklet_from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(booklets)
+}
+
+/// Checks that `collector_id`, if given, is a user account.
+fn ensure_collector(conn: &Connection, collector_id: Option<i32>) -> Result<(), BmsError> {
+    let Some(collector_id) = collector_id else {
+        return Ok(());
+    };
+
+    let exists: bool = conn
+        .prepare_cached("SELECT EXISTS (SELECT 1 FROM users WHERE id = ?1)")?
+        .query_row(params![collector_id], |row| row.get(0))?;
+    if !exists {
+        return Err(BmsError::invalid("collector_id", "Choose the user who will issue these receipts"));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn fetch_or_booklets_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String) -> Result<Vec<OrBooklet>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
+
+    Ok(load_booklets(&conn, None)?)
+}
+
+/// Registers a booklet of receipts. Incomes already recorded under numbers
+/// in its series are counted against it.
+#[tauri::command]
+pub fn register_or_booklet_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, booklet: OrBooklet) -> Result<i64, BmsError> {
+    let user = session.require(&token, TREASURY)?;
+    booklet.validate()?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+    ensure_collector(&tx, booklet.collector_id)?;
+
+    let overlapping: Option<(i32, i32)> = tx
+        .prepare_cached(
+            "SELECT series_start, series_end FROM or_booklets
+             WHERE series_start <= ?2 AND series_end >= ?1
+             ORDER BY series_start LIMIT 1",
+        )?
+        .query_row(params![booklet.series_start, booklet.series_end], |row| Ok((row.get(0)?, row.get(1)?)))
+        .optional()?;
+    if let Some((start, end)) = overlapping {
+        return Err(BmsError::conflict(format!(
+            "Receipts {} to {} overlap the booklet for {} to {}",
+            booklet.series_start, booklet.series_end, start, end
+        )));
+    }
+
+    tx.execute_cached(
+        "INSERT INTO or_booklets (series_start, series_end, collector_id) VALUES (?1, ?2, ?3)",
+        params![booklet.series_start, booklet.series_end, booklet.collector_id],
+    )?;
+    let id = tx.last_insert_rowid();
+    tx.execute_cached(
+        "UPDATE incomes SET booklet_id = ?1 WHERE or_number BETWEEN ?2 AND ?3",
+        params![id, booklet.series_start, booklet.series_end],
+    )?;
+
+    record_change(&tx, &user, "or_booklets", id, Operation::Create, None)?;
+    tx.commit()?;
+
+    Ok(id)
+}
+
+/// Hands a booklet to another collector, or leaves it unassigned when
+/// `collector_id` is None.
+#[tauri::command]
+pub fn assign_or_booklet_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, booklet_id: i32, collector_id: Option<i32>) -> Result<(), BmsError> {
+    let user = session.require(&token, TREASURY)?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+    ensure_collector(&tx, collector_id)?;
+
+    let before = snapshot(&tx, "or_booklets", booklet_id.into())?;
+    if before.is_none() {
+        return Err(BmsError::not_found("OR booklet not found"));
+    }
+    tx.execute_cached(
+        "UPDATE or_booklets SET collector_id = ?1 WHERE id = ?2",
+        params![collector_id, booklet_id],
+    )?;
+
+    record_change(&tx, &user, "or_booklets", booklet_id.into(), Operation::Update, before)?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+/// Cancels a blank receipt that was spoiled or lost, so its number is
+/// accounted for and never issued.
+#[tauri::command]
+pub fn cancel_receipt_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, or_number: i32, reason: String) -> Result<(), BmsError> {
+    let user = session.require(&token, TREASURY)?;
+    Validator::new().required("reason", &reason).finish()?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+
+    let booklet_id = booklet_of(&tx, or_number)?
+        .ok_or_else(|| BmsError::invalid("or_number", format!("OR number {} is not in any registered booklet", or_number)))?;
+    if or_number_taken(&tx, or_number, None)? {
+        return Err(BmsError::conflict(format!(
+            "OR number {} has already been used or cancelled; void the income instead",
+            or_number
+        )));
+    }
+
+    tx.execute_cached(
+        "INSERT INTO cancelled_receipts (or_number, booklet_id, reason, user_id, username) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![or_number, booklet_id, reason.trim(), user.id, user.username],
+    )?;
+    let id = tx.last_insert_rowid();
+
+    record_change(&tx, &user, "cancelled_receipts", id, Operation::Create, None)?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+/// Splits the numbers of a booklet that were never taken into the gaps
+/// before the last one used and the run after it.
+fn unaccounted(booklet: &OrBooklet, taken: &[i32]) -> (Vec<ReceiptRange>, Option<ReceiptRange>) {
+    let mut gaps = Vec::new();
+    // None once the highest possible number has been used.
+    let mut expected = Some(booklet.series_start);
+    for &number in taken {
+        let Some(from) = expected else {
+            break;
+        };
+        if number > from {
+            gaps.push(ReceiptRange { from, to: number - 1 });
+        }
+        expected = number.checked_add(1).map(|next| from.max(next));
+    }
+
+    let unused = expected
+        .filter(|from| *from <= booklet.series_end)
+        .map(|from| ReceiptRange { from, to: booklet.series_end });
+    (gaps, unused)
+}
+
+fn booklet_report(conn: &Connection, booklet: OrBooklet) -> rusqlite::Result<BookletReport> {
+    let id = booklet.id;
+
+    let taken: Vec<i32> = conn
+        .prepare_cached(&format!(
+            "{} SELECT or_number FROM used WHERE booklet_id = ?1 ORDER BY or_number",
+            BOOKLET_USAGE
+        ))?
+        .query_map(params![id], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let issued: i32 = conn
+        .prepare_cached("SELECT COUNT(DISTINCT or_number) FROM incomes WHERE booklet_id = ?1")?
+        .query_row(params![id], |row| row.get(0))?;
+
+    let voided = conn
+        .prepare_cached(
+            "SELECT or_number, id, amount, void_reason, voided_at FROM incomes
+             WHERE booklet_id = ?1 AND voided_at IS NOT NULL
+             ORDER BY or_number",
+        )?
+        .query_map(params![id], |row| {
+            Ok(VoidedReceipt {
+                or_number: row.get(0)?,
+                income_id: row.get(1)?,
+                amount: row.get(2)?,
+                reason: row.get(3)?,
+                voided_at: row.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let cancelled = conn
+        .prepare_cached(
+            "SELECT or_number, reason, username, cancelled_at FROM cancelled_receipts
+             WHERE booklet_id = ?1
+             ORDER BY or_number",
+        )?
+        .query_map(params![id], |row| {
+            Ok(CancelledReceipt {
+                or_number: row.get(0)?,
+                reason: row.get(1)?,
+                username: row.get(2)?,
+                cancelled_at: row.get(3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let duplicates = conn
+        .prepare_cached(
+            "SELECT DISTINCT or_number FROM incomes
+             WHERE booklet_id = ?1 AND duplicate_of IS NOT NULL
+             ORDER BY or_number",
+        )?
+        .query_map(params![id], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let (gaps, unused) = unaccounted(&booklet, &taken);
+    Ok(BookletReport {
+        booklet,
+        issued,
+        voided,
+        cancelled,
+        gaps,
+        unused,
+        duplicates,
+    })
+}
+
+/// Accounts for every receipt number of one booklet, or of all of them, for
+/// the Report of Collections and Deposits.
+#[tauri::command]
+pub fn fetch_receipt_report_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, booklet_id: Option<i32>) -> Result<Vec<BookletReport>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
+
+    let booklets = load_booklets(&conn, booklet_id)?;
+    if booklet_id.is_some() && booklets.is_empty() {
+        return Err(BmsError::not_found("OR booklet not found"));
+    }
+
+    let mut reports = Vec::new();
+    for booklet in booklets {
+        reports.push(booklet_report(&conn, booklet)?);
+    }
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migration::migrate;
+
+    fn booklet(series_start: i32, series_end: i32) -> OrBooklet {
+        OrBooklet {
+            id: None,
+            series_start,
+            series_end,
+            collector_id: None,
+            collector: None,
+            registered_at: String::new(),
+            used: 0,
+            next_number: None,
+        }
+    }
+
+    #[test]
+    fn splits_unused_numbers_into_gaps_and_the_rest() {
+        let (gaps, unused) = unaccounted(&booklet(100, 149), &[100, 103, 104, 110]);
+        assert_eq!(gaps, vec![ReceiptRange { from: 101, to: 102 }, ReceiptRange { from: 105, to: 109 }]);
+        assert_eq!(unused, Some(ReceiptRange { from: 111, to: 149 }));
+    }
+
+    #[test]
+    fn a_fresh_booklet_is_all_unused() {
+        let (gaps, unused) = unaccounted(&booklet(1, 50), &[]);
+        assert!(gaps.is_empty());
+        assert_eq!(unused, Some(ReceiptRange { from: 1, to: 50 }));
+    }
+
+    #[test]
+    fn a_used_up_booklet_has_nothing_left() {
+        let (gaps, unused) = unaccounted(&booklet(1, 3), &[1, 2, 3]);
+        assert!(gaps.is_empty());
+        assert_eq!(unused, None);
+    }
+
+    #[test]
+    fn the_highest_possible_number_does_not_overflow() {
+        let (gaps, unused) = unaccounted(&booklet(i32::MAX - 4, i32::MAX), &[i32::MAX - 3, i32::MAX]);
+        assert_eq!(
+            gaps,
+            vec![
+                ReceiptRange { from: i32::MAX - 4, to: i32::MAX - 4 },
+                ReceiptRange { from: i32::MAX - 2, to: i32::MAX - 1 },
+            ]
+        );
+        assert_eq!(unused, None);
+    }
+
+    #[test]
+    fn reports_a_booklet_from_the_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "foreign_keys", "ON").unwrap();
+        migrate(&mut conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO or_booklets (series_start, series_end) VALUES (100, 109);
+             INSERT INTO incomes (type_, amount, or_number, received_from, received_by, category, date, booklet_id)
+                 VALUES ('Fees', 5000, 100, 'Juan Cruz', 'treasurer', 'Fees', '2026-01-05', 1),
+                        ('Fees', 5000, 104, 'Ana Reyes', 'treasurer', 'Fees', '2026-01-06', 1);
+             INSERT INTO cancelled_receipts (or_number, booklet_id, reason, user_id, username)
+                 VALUES (101, 1, 'Spoiled', 1, 'treasurer');",
+        )
+        .unwrap();
+
+        let booklet = load_booklets(&conn, Some(1)).unwrap().pop().unwrap();
+        assert_eq!(booklet.next_number, Some(105));
+        let report = booklet_report(&conn, booklet).unwrap();
+        assert_eq!(report.issued, 2);
+        assert_eq!(report.cancelled.len(), 1);
+        assert_eq!(report.gaps, vec![ReceiptRange { from: 102, to: 103 }]);
+        assert_eq!(report.unused, Some(ReceiptRange { from: 105, to: 109 }));
+    }
+}
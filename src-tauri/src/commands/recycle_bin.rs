@@ -0,0 +1,67 @@
+use crate::auth::permissions::{ADMINISTRATORS, ANY_ROLE, CERTIFICATE_ISSUERS, RECORD_EDITORS, TREASURY};
+use crate::auth::session::SessionState;
+use crate::database::connection::DbPool;
+use crate::database::recycle_bin::{deleted_rows, known_table, purge, SOFT_DELETE_TABLES};
+use crate::error::BmsError;
+use crate::models::recycle_bin::DeletedRecord;
+use crate::models::user::Role;
+use tauri::State;
+
+/// How long deleted rows are kept when the caller does not say otherwise.
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+/// The roles whose `restore_*` command covers `table`.
+fn restorers(table: &str) -> &'static [Role] {
+    match table {
+        "incomes" | "expenses" => TREASURY,
+        "certificates" => CERTIFICATE_ISSUERS,
+        "officials" => ADMINISTRATORS,
+        _ => RECORD_EDITORS,
+    }
+}
+
+/// Deleted rows of `table_name`, or of every table the caller can restore
+/// rows of.
+#[tauri::command]
+pub fn fetch_recycle_bin_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, table_name: Option<String>) -> Result<Vec<DeletedRecord>, BmsError> {
+    let user = session.require(&token, ANY_ROLE)?;
+
+    let tables: Vec<&'static str> = match table_name {
+        Some(name) => {
+            let table = known_table(&name).ok_or_else(|| BmsError::invalid("table_name", format!("Unknown table: {}", name)))?;
+            session.require(&token, restorers(table))?;
+            vec![table]
+        }
+        None => SOFT_DELETE_TABLES
+            .iter()
+            .copied()
+            .filter(|table| restorers(table).contains(&user.role))
+            .collect(),
+    };
+    if tables.is_empty() {
+        return Err(BmsError::unauthorized("You do not have permission to do this"));
+    }
+    let conn = pool.get()?;
+
+    let mut records = Vec::new();
+    for table in tables {
+        records.extend(deleted_rows(&conn, table)?);
+    }
+
+    Ok(records)
+}
+
+/// Permanently removes rows deleted more than `retention_days` ago and
+/// returns how many were removed.
+#[tauri::command]
+pub fn purge_deleted_records_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, retention_days: Option<i64>) -> Result<usize, BmsError> {
+    let user = session.require(&token, ADMINISTRATORS)?;
+    let conn = pool.get()?;
+
+    let retention_days = retention_days.unwrap_or(DEFAULT_RETENTION_DAYS);
+    if retention_days < 0 {
+        return Err(BmsError::invalid("retention_days", "Retention period cannot be negative"));
+    }
+
+    Ok(purge(&conn, &user, retention_days)?)
+}
@@ -0,0 +1,251 @@
+use crate::auth::permissions::ANY_ROLE;
+use crate::auth::session::SessionState;
+use crate::database::blotter::parse_optional_column;
+use crate::database::connection::DbPool;
+use crate::error::BmsError;
+use crate::models::budget::AccountCode;
+use crate::models::money::Money;
+use crate::models::report::{
+    CashFlowMonth, CategoryShare, Comparison, Ledger, ReceiptsAndExpenditures, ReportLine, ReportPeriod, YearOverYear,
+};
+use crate::validation::Validate;
+use rusqlite::{params, Connection};
+use std::collections::BTreeMap;
+use tauri::State;
+
+/// Money actually received in `?1`..`?2`: voided receipts and deleted
+/// incomes do not count.
+const RECEIPTS_IN_PERIOD: &str = "FROM incomes
+    WHERE deleted_at IS NULL AND voided_at IS NULL AND date(date) BETWEEN date(?1) AND date(?2)";
+
+const EXPENDITURES_IN_PERIOD: &str = "FROM expenses e
+    LEFT JOIN appropriations a ON a.id = e.appropriation_id
+    WHERE e.deleted_at IS NULL AND date(e.date) BETWEEN date(?1) AND date(?2)";
+
+/// Receipts per income category.
+fn receipt_lines(conn: &Connection, period: &ReportPeriod) -> rusqlite::Result<Vec<ReportLine>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT category, SUM(amount), COUNT(*) {} GROUP BY category ORDER BY category",
+        RECEIPTS_IN_PERIOD
+    ))?;
+    let lines = stmt
+        .query_map(params![period.date_from, period.date_to], |row| {
+            Ok(ReportLine {
+                label: row.get(0)?,
+                account_code: None,
+                amount: row.get(1)?,
+                entries: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(lines)
+}
+
+/// Expenditures per account class, with expenses not charged to an
+/// appropriation listed by category after them.
+fn expenditure_lines(conn: &Connection, period: &ReportPeriod) -> rusqlite::Result<Vec<ReportLine>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT a.account_code, CASE WHEN a.id IS NULL THEN e.category END, SUM(e.amount), COUNT(*)
+         {} GROUP BY 1, 2",
+        EXPENDITURES_IN_PERIOD
+    ))?;
+    let mut lines: Vec<ReportLine> = stmt
+        .query_map(params![period.date_from, period.date_to], |row| {
+            let account_code: Option<AccountCode> = parse_optional_column(row, 0)?;
+            let category: Option<String> = row.get(1)?;
+            Ok(ReportLine {
+                label: account_code.map(|c| c.label().to_string()).or(category).unwrap_or_default(),
+                account_code,
+                amount: row.get(2)?,
+                entries: row.get(3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    lines.sort_by(|a, b| {
+        (a.account_code.is_none(), a.account_code, &a.label).cmp(&(b.account_code.is_none(), b.account_code, &b.label))
+    });
+    Ok(lines)
+}
+
+/// Totals per `YYYY-MM` of the rows `from` selects.
+fn monthly_totals(conn: &Connection, period: &ReportPeriod, date: &str, amount: &str, from: &str) -> rusqlite::Result<BTreeMap<String, Money>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT strftime('%Y-%m', {date}), SUM({amount}) {from} GROUP BY 1"
+    ))?;
+    let totals = stmt
+        .query_map(params![period.date_from, period.date_to], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(totals)
+}
+
+/// Every `YYYY-MM` from the month of `date_from` to that of `date_to`.
+fn months(period: &ReportPeriod) -> Vec<String> {
+    let year_month = |date: &str| -> (i32, u32) {
+        (
+            date.get(..4).and_then(|y| y.parse().ok()).unwrap_or_default(),
+            date.get(5..7).and_then(|m| m.parse().ok()).unwrap_or(1),
+        )
+    };
+    let (mut year, mut month) = year_month(&period.date_from);
+    let end = year_month(&period.date_to);
+
+    let mut months = Vec::new();
+    while (year, month) <= end {
+        months.push(format!("{:04}-{:02}", year, month));
+        if month == 12 {
+            year += 1;
+            month = 1;
+        } else {
+            month += 1;
+        }
+    }
+    months
+}
+
+/// `part` as a percent of `whole`, to two decimals.
+fn percent(part: Money, whole: Money) -> Option<f64> {
+    (whole != Money::ZERO).then(|| (part.centavos() as f64 * 10_000.0 / whole.centavos() as f64).round() / 100.0)
+}
+
+fn comparison(label: String, current: Money, previous: Money) -> Comparison {
+    Comparison {
+        label,
+        current,
+        previous,
+        change: current - previous,
+        change_percent: percent(current - previous, previous),
+    }
+}
+
+/// Lines of both periods matched by label.
+fn compare_lines(current: Vec<ReportLine>, previous: Vec<ReportLine>) -> Vec<Comparison> {
+    let mut amounts: BTreeMap<String, (Money, Money)> = BTreeMap::new();
+    for line in current {
+        amounts.entry(line.label).or_default().0 += line.amount;
+    }
+    for line in previous {
+        amounts.entry(line.label).or_default().1 += line.amount;
+    }
+
+    amounts
+        .into_iter()
+        .map(|(label, (current, previous))| comparison(label, current, previous))
+        .collect()
+}
+
+#[tauri::command]
+pub fn fetch_receipts_and_expenditures_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, period: ReportPeriod) -> Result<ReceiptsAndExpenditures, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    period.validate()?;
+    let conn = pool.get()?;
+
+    let receipts = receipt_lines(&conn, &period)?;
+    let expenditures = expenditure_lines(&conn, &period)?;
+    let total_receipts: Money = receipts.iter().map(|l| l.amount).sum();
+    let total_expenditures: Money = expenditures.iter().map(|l| l.amount).sum();
+
+    Ok(ReceiptsAndExpenditures {
+        period,
+        receipts,
+        total_receipts,
+        expenditures,
+        total_expenditures,
+        excess: total_receipts - total_expenditures,
+    })
+}
+
+/// Receipts and expenditures for every month of the period, empty months
+/// included.
+#[tauri::command]
+pub fn fetch_monthly_cash_flow_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, period: ReportPeriod) -> Result<Vec<CashFlowMonth>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    period.validate()?;
+    let conn = pool.get()?;
+
+    let receipts = monthly_totals(&conn, &period, "date", "amount", RECEIPTS_IN_PERIOD)?;
+    let expenditures = monthly_totals(&conn, &period, "e.date", "e.amount", EXPENDITURES_IN_PERIOD)?;
+
+    let mut cumulative = Money::ZERO;
+    let flow = months(&period)
+        .into_iter()
+        .map(|month| {
+            let receipts = receipts.get(&month).copied().unwrap_or_default();
+            let expenditures = expenditures.get(&month).copied().unwrap_or_default();
+            let net = receipts - expenditures;
+            cumulative += net;
+            CashFlowMonth { month, receipts, expenditures, net, cumulative }
+        })
+        .collect();
+
+    Ok(flow)
+}
+
+/// Receipts or expenditures per category, largest first.
+#[tauri::command]
+pub fn fetch_category_breakdown_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, period: ReportPeriod, ledger: Ledger) -> Result<Vec<CategoryShare>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    period.validate()?;
+    let conn = pool.get()?;
+
+    let (category, amount, from) = match ledger {
+        Ledger::Receipts => ("category", "amount", RECEIPTS_IN_PERIOD),
+        Ledger::Expenditures => ("e.category", "e.amount", EXPENDITURES_IN_PERIOD),
+    };
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {category}, SUM({amount}), COUNT(*) {from} GROUP BY 1 ORDER BY 2 DESC, 1"
+    ))?;
+    let totals: Vec<(String, Money, i64)> = stmt
+        .query_map(params![period.date_from, period.date_to], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let total: Money = totals.iter().map(|(_, amount, _)| *amount).sum();
+    let breakdown = totals
+        .into_iter()
+        .map(|(category, amount, entries)| CategoryShare {
+            category,
+            amount,
+            entries,
+            share: percent(amount, total).unwrap_or_default(),
+        })
+        .collect();
+
+    Ok(breakdown)
+}
+
+/// The period's receipts and expenditures against the same dates a year
+/// earlier.
+#[tauri::command]
+pub fn fetch_year_over_year_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, period: ReportPeriod) -> Result<YearOverYear, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    period.validate()?;
+    let conn = pool.get()?;
+
+    let previous_period = conn
+        .prepare_cached("SELECT date(?1, '-1 year'), date(?2, '-1 year')")?
+        .query_row(params![period.date_from, period.date_to], |row| {
+            Ok(ReportPeriod {
+                date_from: row.get(0)?,
+                date_to: row.get(1)?,
+            })
+        })?;
+
+    let receipts = compare_lines(receipt_lines(&conn, &period)?, receipt_lines(&conn, &previous_period)?);
+    let expenditures = compare_lines(expenditure_lines(&conn, &period)?, expenditure_lines(&conn, &previous_period)?);
+    let total = |lines: &[Comparison], label: &str| {
+        comparison(
+            label.to_string(),
+            lines.iter().map(|c| c.current).sum(),
+            lines.iter().map(|c| c.previous).sum(),
+        )
+    };
+
+    Ok(YearOverYear {
+        total_receipts: total(&receipts, "Total Receipts"),
+        total_expenditures: total(&expenditures, "Total Expenditures"),
+        period,
+        previous_period,
+        receipts,
+        expenditures,
+    })
+}
@@ -1,107 +1,134 @@
-use rusqlite::params;
-use serde::{Deserialize, Serialize};
-
-use crate::database::connection::establish_connection;
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Resident {
-    pub id: Option<i32>,
-    pub prefix: String,
-    pub first_name: String,
-    pub middle_name: Option<String>,
-    pub last_name: String,
-    pub suffix: Option<String>,
-    pub civil_status: String,
-    pub gender: String,
-    pub nationality: String,
-    pub mobile_number: String,
-    pub date_of_birth: String,
-    pub town_of_birth: String,
-    pub province_of_birth: String,
-    pub zone: String,
-    pub barangay: String,
-    pub town: String,
-    pub province: String,
-    pub father_prefix: String,
-    pub father_first_name: String,
-    pub father_middle_name: String,
-    pub father_last_name: String,
-    pub father_suffix: String,
-    pub mother_prefix: String,
-    pub mother_first_name: String,
-    pub mother_middle_name: String,
-    pub mother_last_name: String,
-    pub status: String,
-    pub photo: Option<String>,
-    pub is_registered_voter: bool,
-    pub is_pwd: bool,
-    pub is_senior: bool,
-}
+use rusqlite::{params, Row};
 
-#[tauri::command]
-pub fn fetch_all_residents_command() -> Result<Vec<Resident>, String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+use crate::auth::permissions::{ANY_ROLE, RECORD_EDITORS};
+use crate::auth::session::SessionState;
+use crate::database::audit::{record_change, snapshot, Operation};
+use crate::database::connection::{DbPool, ExecuteCached};
+use crate::database::query::{fetch_page, Filters, Listing};
+use crate::database::recycle_bin::{restore, soft_delete};
+use crate::error::BmsError;
+use crate::models::query::{Page, PageRequest};
+use crate::models::resident::{Resident, ResidentFilter};
+use crate::validation::Validate;
+use tauri::State;
 
-    let mut stmt = conn.prepare(
-        "SELECT id, prefix, first_name, middle_name, last_name, suffix, civil_status, gender, nationality,
+const RESIDENT_COLUMNS: &str = "id, prefix, first_name, middle_name, last_name, suffix, civil_status, gender, nationality,
        mobile_number, date_of_birth, town_of_birth, province_of_birth, zone, barangay, town, province,
        father_prefix, father_first_name, father_middle_name, father_last_name, father_suffix,
        mother_prefix, mother_first_name, mother_middle_name, mother_last_name, status, photo,
-       is_registered_voter, is_pwd, is_senior
-FROM residents"
-    ).map_err(|e| e.to_string())?;
+       is_registered_voter, is_pwd, is_senior";
+
+// Same columns with the base64 photo left out; list views fetch photos one
+// at a time through `fetch_resident_photo_command`.
+const RESIDENT_LISTING: Listing = Listing {
+    table: "residents",
+    columns: "id, prefix, first_name, middle_name, last_name, suffix, civil_status, gender, nationality,
+       mobile_number, date_of_birth, town_of_birth, province_of_birth, zone, barangay, town, province,
+       father_prefix, father_first_name, father_middle_name, father_last_name, father_suffix,
+       mother_prefix, mother_first_name, mother_middle_name, mother_last_name, status, NULL,
+       is_registered_voter, is_pwd, is_senior",
+    sortable: &["last_name", "first_name", "date_of_birth", "zone", "status", "gender", "civil_status"],
+    default_sort: "last_name",
+};
+
+fn resident_from_row(row: &Row) -> rusqlite::Result<Resident> {
+    Ok(Resident {
+        id: row.get(0)?,
+        prefix: row.get(1)?,
+        first_name: row.get(2)?,
+        middle_name: row.get(3)?,
+        last_name: row.get(4)?,
+        suffix: row.get(5)?,
+        civil_status: row.get(6)?,
+        gender: row.get(7)?,
+        nationality: row.get(8)?,
+        mobile_number: row.get(9)?,
+        date_of_birth: row.get(10)?,
+        town_of_birth: row.get(11)?,
+        province_of_birth: row.get(12)?,
+        zone: row.get(13)?,
+        barangay: row.get(14)?,
+        town: row.get(15)?,
+        province: row.get(16)?,
+        father_prefix: row.get(17)?,
+        father_first_name: row.get(18)?,
+        father_middle_name: row.get(19)?,
+        father_last_name: row.get(20)?,
+        father_suffix: row.get(21)?,
+        mother_prefix: row.get(22)?,
+        mother_first_name: row.get(23)?,
+        mother_middle_name: row.get(24)?,
+        mother_last_name: row.get(25)?,
+        status: row.get(26)?,
+        photo: row.get(27)?,
+        is_registered_voter: row.get(28)?,
+        is_pwd: row.get(29)?,
+        is_senior: row.get(30)?,
+    })
+}
+
+#[tauri::command]
+pub fn fetch_all_residents_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String) -> Result<Vec<Resident>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
+
+    let mut stmt = conn.prepare_cached(
+        &format!("SELECT {} FROM residents WHERE deleted_at IS NULL", RESIDENT_COLUMNS)
+    )?;
 
     let resident_iter = stmt
-        .query_map([], |row| {
-            Ok(Resident {
-                id: row.get(0)?,
-                prefix: row.get(1)?,
-                first_name: row.get(2)?,
-                middle_name: row.get(3)?,
-                last_name: row.get(4)?,
-                suffix: row.get(5)?,
-                civil_status: row.get(6)?,
-                gender: row.get(7)?,
-                nationality: row.get(8)?,
-                mobile_number: row.get(9)?,
-                date_of_birth: row.get(10)?,
-                town_of_birth: row.get(11)?,
-                province_of_birth: row.get(12)?,
-                zone: row.get(13)?,
-                barangay: row.get(14)?,
-                town: row.get(15)?,
-                province: row.get(16)?,
-                father_prefix: row.get(17)?,
-                father_first_name: row.get(18)?,
-                father_middle_name: row.get(19)?,
-                father_last_name: row.get(20)?,
-                father_suffix: row.get(21)?,
-                mother_prefix: row.get(22)?,
-                mother_first_name: row.get(23)?,
-                mother_middle_name: row.get(24)?,
-                mother_last_name: row.get(25)?,
-                status: row.get(26)?,
-                photo: row.get(27)?,
-                is_registered_voter: row.get(28)?,
-                is_pwd: row.get(29)?,
-                is_senior: row.get(30)?,
-            })
-        })
-        .map_err(|e| e.to_string())?;
+        .query_map([], resident_from_row)?;
 
     let mut residents = Vec::new();
     for resident in resident_iter {
-        residents.push(resident.map_err(|e| e.to_string())?);
+        residents.push(resident?);
     }
 
     Ok(residents)
 }
 
 #[tauri::command]
-pub fn insert_resident_command(resident: Resident) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn query_residents_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, page: PageRequest, filter: Option<ResidentFilter>) -> Result<Page<Resident>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
+    let filter = filter.unwrap_or_default();
+
+    let mut filters = Filters::new();
+    filters
+        .raw("deleted_at IS NULL")
+        .opt("zone = ?", filter.zone)
+        .opt("status = ?", filter.status)
+        .opt("gender = ?", filter.gender)
+        .opt("civil_status = ?", filter.civil_status)
+        .opt("is_registered_voter = ?", filter.is_registered_voter)
+        .opt("is_pwd = ?", filter.is_pwd)
+        .opt("is_senior = ?", filter.is_senior)
+        .opt("date(date_of_birth) >= date(?)", filter.born_from)
+        .opt("date(date_of_birth) <= date(?)", filter.born_to);
+
+    fetch_page(&conn, &RESIDENT_LISTING, &filters, &page, resident_from_row)
+}
+
+#[tauri::command]
+pub fn fetch_resident_photo_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<Option<String>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
 
-    conn.execute(
+    let photo = conn
+        .prepare_cached("SELECT photo FROM residents WHERE id = ?1 AND deleted_at IS NULL")?
+        .query_row(params![id], |row| row.get(0))?;
+
+    Ok(photo)
+}
+
+#[tauri::command]
+pub fn insert_resident_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, resident: Resident) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    resident.validate()?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+
+    tx.execute_cached(
     "INSERT INTO residents (
         prefix, first_name, middle_name, last_name, suffix, civil_status, gender, nationality,
         mobile_number, date_of_birth, town_of_birth, province_of_birth, zone, barangay, town, province,
@@ -123,7 +150,7 @@ pub fn insert_resident_command(resident: Resident) -> Result<(), String> {
         resident.date_of_birth,
         resident.town_of_birth,
         resident.province_of_birth,
-        resident.zone,
+        resident.zone.trim(),
         resident.barangay,
         resident.town,
         resident.province,
@@ -142,16 +169,24 @@ pub fn insert_resident_command(resident: Resident) -> Result<(), String> {
         resident.is_pwd,
         resident.is_senior,
     ],
-).map_err(|e| e.to_string())?;
+)?;
+
+    record_change(&tx, &user, "residents", tx.last_insert_rowid(), Operation::Create, None)?;
+    tx.commit()?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn update_resident_command(resident: Resident) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn update_resident_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, resident: Resident) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    resident.validate()?;
+    let id = resident.id.ok_or_else(|| BmsError::invalid("id", "Resident id is required"))?;
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+    let before = snapshot(&tx, "residents", id.into())?;
 
-    conn.execute(
+    let changed = tx.execute_cached(
     "UPDATE residents SET
         prefix = ?1, first_name = ?2, middle_name = ?3, last_name = ?4, suffix = ?5, civil_status = ?6,
         gender = ?7, nationality = ?8, mobile_number = ?9, date_of_birth = ?10, town_of_birth = ?11,
@@ -159,7 +194,7 @@ pub fn update_resident_command(resident: Resident) -> Result<(), String> {
         father_prefix = ?17, father_first_name = ?18, father_middle_name = ?19, father_last_name = ?20, father_suffix = ?21,
         mother_prefix = ?22, mother_first_name = ?23, mother_middle_name = ?24, mother_last_name = ?25,
         status = ?26, photo = ?27, is_registered_voter = ?28, is_pwd = ?29, is_senior = ?30
-     WHERE id = ?31",
+     WHERE id = ?31 AND deleted_at IS NULL",
     params![
         resident.prefix,
         resident.first_name,
@@ -173,7 +208,7 @@ pub fn update_resident_command(resident: Resident) -> Result<(), String> {
         resident.date_of_birth,
         resident.town_of_birth,
         resident.province_of_birth,
-        resident.zone,
+        resident.zone.trim(),
         resident.barangay,
         resident.town,
         resident.province,
@@ -193,26 +228,44 @@ pub fn update_resident_command(resident: Resident) -> Result<(), String> {
         resident.is_senior,
         resident.id
     ],
-).map_err(|e| e.to_string())?;
+)?;
+    if changed == 0 {
+        return Err(BmsError::not_found("Resident not found"));
+    }
+
+    record_change(&tx, &user, "residents", id.into(), Operation::Update, before)?;
+    tx.commit()?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn save_resident_command(resident: Resident) -> Result<(), String> {
+pub fn save_resident_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, resident: Resident) -> Result<(), BmsError> {
     if resident.id.is_some() {
-        update_resident_command(resident)
+        update_resident_command(pool, session, token, resident)
     } else {
-        insert_resident_command(resident)
+        insert_resident_command(pool, session, token, resident)
     }
 }
 
 #[tauri::command]
-pub fn delete_resident_command(id: i32) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn delete_resident_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    let conn = pool.get()?;
 
-    conn.execute("DELETE FROM residents WHERE id = ?", params![id])
-        .map_err(|e| e.to_string())?;
+    soft_delete(&conn, &user, "residents", id.into())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn restore_resident_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, id: i32) -> Result<(), BmsError> {
+    let user = session.require(&token, RECORD_EDITORS)?;
+    let conn = pool.get()?;
+
+    if !restore(&conn, &user, "residents", id.into())? {
+        return Err(BmsError::not_found("Resident is not in the recycle bin"));
+    }
 
     Ok(())
 }
@@ -0,0 +1,74 @@
+use crate::auth::permissions::ANY_ROLE;
+use crate::auth::session::SessionState;
+use crate::database::connection::DbPool;
+use crate::error::BmsError;
+use crate::models::search::{SearchHit, SearchKind};
+use rusqlite::params;
+use tauri::State;
+
+const DEFAULT_LIMIT: i64 = 25;
+const MAX_LIMIT: i64 = 100;
+
+#[tauri::command]
+pub fn global_search_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, query: String, limit: Option<i64>) -> Result<Vec<SearchHit>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+
+    let Some(match_expr) = fts_query(&query) else {
+        return Ok(Vec::new());
+    };
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let conn = pool.get()?;
+
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT 'resident', rowid, title, snippet(residents_fts, -1, '<mark>', '</mark>', '…', 12), bm25(residents_fts)
+             FROM residents_fts WHERE residents_fts MATCH ?1
+             UNION ALL
+             SELECT 'blotter', rowid, title, snippet(blotters_fts, -1, '<mark>', '</mark>', '…', 12), bm25(blotters_fts)
+             FROM blotters_fts WHERE blotters_fts MATCH ?1
+             UNION ALL
+             SELECT 'certificate', rowid, title, snippet(certificates_fts, -1, '<mark>', '</mark>', '…', 12), bm25(certificates_fts)
+             FROM certificates_fts WHERE certificates_fts MATCH ?1
+             ORDER BY 5
+             LIMIT ?2",
+        )?;
+
+    let hits_iter = stmt
+        .query_map(params![match_expr, limit], |row| {
+            let kind: String = row.get(0)?;
+            Ok(SearchHit {
+                kind: match kind.as_str() {
+                    "resident" => SearchKind::Resident,
+                    "blotter" => SearchKind::Blotter,
+                    _ => SearchKind::Certificate,
+                },
+                id: row.get(1)?,
+                title: row.get(2)?,
+                snippet: row.get(3)?,
+                rank: row.get(4)?,
+            })
+        })?;
+
+    let mut hits = Vec::new();
+    for hit in hits_iter {
+        hits.push(hit?);
+    }
+
+    Ok(hits)
+}
+
+/// Turns what the clerk typed into an FTS5 query where every word must match
+/// as a prefix, so "dela cru" finds "Dela Cruz". Words are quoted so FTS
+/// operators and punctuation in the input are taken literally.
+fn fts_query(input: &str) -> Option<String> {
+    let terms: Vec<String> = input
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
@@ -1,15 +1,21 @@
-use crate::database::connection::establish_connection;
-use crate::models::settings::Settings;
-use rusqlite::params;
+use crate::auth::permissions::{ADMINISTRATORS, ANY_ROLE};
+use crate::auth::session::SessionState;
+use crate::database::audit::{record_change, snapshot, Operation};
+use crate::database::connection::{DbPool, ExecuteCached};
+use crate::error::BmsError;
+use tauri::State;
+use crate::models::settings::{CertificateFee, Settings};
+use crate::validation::Validate;
+use rusqlite::{params, OptionalExtension};
 
 #[tauri::command]
-pub fn fetch_settings_command() -> Result<Settings, String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn fetch_settings_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String) -> Result<Settings, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
 
     // Try fetching the settings
     let mut stmt = conn
-        .prepare("SELECT id, barangay, municipality, province, phone_number, email, logo, logo_municipality FROM settings WHERE id = 1")
-        .map_err(|e| e.to_string())?;
+        .prepare_cached("SELECT id, barangay, municipality, province, phone_number, email, logo, logo_municipality FROM settings WHERE id = 1")?;
 
     let result = stmt.query_row([], |row| {
         Ok(Settings {
@@ -28,10 +34,10 @@ pub fn fetch_settings_command() -> Result<Settings, String> {
     match result {
         Ok(settings) => Ok(settings),
         Err(_) => {
-            conn.execute(
+            conn.execute_cached(
                 "INSERT INTO settings (id, barangay, municipality, province, phone_number, email, logo, logo_municipality) VALUES (1, '', '', '', '', '', '', '')",
                 [],
-            ).map_err(|e| e.to_string())?;
+            )?;
 
             Ok(Settings {
                 id: Some(1),
@@ -48,11 +54,13 @@ pub fn fetch_settings_command() -> Result<Settings, String> {
 }
 
 #[tauri::command]
-pub fn save_settings_command(settings: Settings) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn save_settings_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, settings: Settings) -> Result<(), BmsError> {
+    session.require(&token, ADMINISTRATORS)?;
+    settings.validate()?;
+    let conn = pool.get()?;
 
     if settings.id.is_some() {
-        conn.execute(
+        conn.execute_cached(
             "UPDATE settings SET barangay = ?1, municipality = ?2, province = ?3, phone_number = ?4, email = ?5, logo = ?6, logo_municipality = ?7 WHERE id = ?8",
             params![
                 settings.barangay,
@@ -65,10 +73,9 @@ pub fn save_settings_command(settings: Settings) -> Result<(), String> {
                 settings.id
                 
             ],
-        )
-        .map_err(|e| e.to_string())?;
+        )?;
     } else {
-        conn.execute(
+        conn.execute_cached(
             "INSERT INTO settings (barangay, municipality, province, phone_number, email, logo, logo_municipality) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
             params![
                 settings.barangay,
@@ -79,22 +86,88 @@ pub fn save_settings_command(settings: Settings) -> Result<(), String> {
                 settings.logo,
                 settings.logo_municipality
             ],
-        )
-        .map_err(|e| e.to_string())?;
+        )?;
     }
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn fetch_logo_command() -> Result<Option<String>, String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn fetch_logo_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String) -> Result<Option<String>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
 
     let mut stmt = conn
-        .prepare("SELECT logo FROM settings WHERE id = 1")
-        .map_err(|e| e.to_string())?;
+        .prepare_cached("SELECT logo FROM settings WHERE id = 1")?;
 
-    let logo_result: Result<Option<String>, _> = stmt.query_row([], |row| row.get(0));
+    let logo = stmt.query_row([], |row| row.get(0))?;
 
-    logo_result.map_err(|e| e.to_string())
+    Ok(logo)
+}
+
+#[tauri::command]
+pub fn fetch_certificate_fees_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String) -> Result<Vec<CertificateFee>, BmsError> {
+    session.require(&token, ANY_ROLE)?;
+    let conn = pool.get()?;
+
+    let mut stmt = conn.prepare_cached("SELECT type_, amount FROM certificate_fees ORDER BY type_")?;
+    let fees = stmt
+        .query_map([], |row| Ok(CertificateFee { type_: row.get(0)?, amount: row.get(1)? }))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(fees)
+}
+
+/// Replaces the whole fee schedule. Each fee added, changed or dropped gets
+/// its own audit entry; fees left as they were get none.
+#[tauri::command]
+pub fn save_certificate_fees_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: String, fees: Vec<CertificateFee>) -> Result<(), BmsError> {
+    let user = session.require(&token, ADMINISTRATORS)?;
+    for fee in &fees {
+        fee.validate()?;
+    }
+    let conn = pool.get()?;
+    let tx = conn.unchecked_transaction()?;
+
+    let existing: Vec<(i64, String)> = tx
+        .prepare_cached("SELECT id, type_ FROM certificate_fees")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    for (id, type_) in existing {
+        if !fees.iter().any(|fee| fee.type_.trim().eq_ignore_ascii_case(&type_)) {
+            let before = snapshot(&tx, "certificate_fees", id)?;
+            tx.execute_cached("DELETE FROM certificate_fees WHERE id = ?1", params![id])?;
+            record_change(&tx, &user, "certificate_fees", id, Operation::Delete, before)?;
+        }
+    }
+
+    for fee in &fees {
+        let id: Option<i64> = tx
+            .prepare_cached("SELECT id FROM certificate_fees WHERE type_ = ?1")?
+            .query_row(params![fee.type_.trim()], |row| row.get(0))
+            .optional()?;
+        match id {
+            Some(id) => {
+                let before = snapshot(&tx, "certificate_fees", id)?;
+                let changed = tx.execute_cached(
+                    "UPDATE certificate_fees SET type_ = ?1, amount = ?2
+                     WHERE id = ?3 AND (type_ <> ?1 COLLATE BINARY OR amount <> ?2)",
+                    params![fee.type_.trim(), fee.amount, id],
+                )?;
+                if changed > 0 {
+                    record_change(&tx, &user, "certificate_fees", id, Operation::Update, before)?;
+                }
+            }
+            None => {
+                tx.execute_cached(
+                    "INSERT INTO certificate_fees (type_, amount) VALUES (?1, ?2)",
+                    params![fee.type_.trim(), fee.amount],
+                )?;
+                record_change(&tx, &user, "certificate_fees", tx.last_insert_rowid(), Operation::Create, None)?;
+            }
+        }
+    }
+    tx.commit()?;
+
+    Ok(())
 }
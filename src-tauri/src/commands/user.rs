@@ -1,30 +1,231 @@
-use crate::database::connection::establish_connection;
-use crate::models::user::User;
-use rusqlite::params;
+use crate::auth::password::{
+    hash_password, verify_dummy, verify_password, PasswordCheck, LOCKOUT_DURATION, MAX_FAILED_ATTEMPTS,
+};
+use crate::auth::permissions::ADMINISTRATORS;
+use crate::auth::session::SessionState;
+use crate::database::connection::{DbPool, ExecuteCached};
+use crate::error::BmsError;
+use crate::models::user::{LoginSession, Role, SessionUser, User};
+use crate::validation::Validate;
+use rusqlite::{params, Connection, OptionalExtension};
+use tauri::State;
 
+/// Creates an account. The very first account is always made an admin so a
+/// fresh install can be set up without a session; after that only admins may
+/// add users.
 #[tauri::command]
-pub fn insert_user_command(user: User) -> Result<(), String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn insert_user_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, token: Option<String>, user: User) -> Result<(), BmsError> {
+    user.validate()?;
+    // Hashed before taking the connection so other commands are not kept
+    // waiting on Argon2.
+    let password_hash = hash_password(&user.password)?;
+    let conn = pool.get()?;
 
-    conn.execute(
-        "INSERT INTO users (username, password) VALUES (?1, ?2)",
-        params![user.username, user.password],
-    ).map_err(|e| e.to_string())?;
+    let user_count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))?;
+    let role = if user_count == 0 {
+        Role::Admin
+    } else {
+        session.require(token.as_deref().unwrap_or_default(), ADMINISTRATORS)?;
+        user.role
+    };
+
+    conn.execute_cached(
+        "INSERT INTO users (username, password, role) VALUES (?1, ?2, ?3)",
+        params![user.username, password_hash, role.as_str()],
+    )?;
 
     Ok(())
 }
 
+/// The password is hashed and checked without holding the connection, so
+/// other commands are not kept waiting on Argon2.
+#[tauri::command]
+pub fn login_command(pool: State<'_, DbPool>, session: State<'_, SessionState>, username: String, password: String) -> Result<LoginSession, BmsError> {
+    let account = {
+        let conn = pool.get()?;
+        find_account(&conn, &username)?
+    };
+    let Some(account) = account else {
+        verify_dummy(&password);
+        return Err(BmsError::unauthorized("Invalid username or password"));
+    };
+
+    let check = verify_password(&account.password_hash, &password);
+    let rehashed = match check {
+        PasswordCheck::ValidLegacy => Some(hash_password(&password)?),
+        PasswordCheck::Valid | PasswordCheck::Invalid => None,
+    };
+
+    let conn = pool.get()?;
+    let user = record_attempt(&conn, account, check, rehashed)?
+        .ok_or_else(|| BmsError::unauthorized("Invalid username or password"))?;
+    let token = session.begin(user.clone())?;
+
+    Ok(LoginSession { token, user })
+}
+
 #[tauri::command]
-pub fn verify_user_credentials_command(username: String, password: String) -> Result<bool, String> {
-    let conn = establish_connection().map_err(|e| e.to_string())?;
+pub fn logout_command(session: State<'_, SessionState>, token: String) -> Result<(), BmsError> {
+    session.end(&token)
+}
 
+#[tauri::command]
+pub fn current_user_command(session: State<'_, SessionState>, token: String) -> Result<SessionUser, BmsError> {
+    session.current(&token)
+}
+
+struct Account {
+    user: SessionUser,
+    password_hash: String,
+}
+
+/// Looks up the account signing in. Returns `None` for an unknown username
+/// and fails if the account is locked.
+fn find_account(conn: &Connection, username: &str) -> Result<Option<Account>, BmsError> {
     let mut stmt = conn
-        .prepare("SELECT COUNT(*) FROM users WHERE username = ?1 AND password = ?2")
-        .map_err(|e| e.to_string())?;
+        .prepare_cached(
+            "SELECT id, password, COALESCE(locked_until > datetime('now'), 0), locked_until, role
+             FROM users WHERE username = ?1",
+        )?;
+
+    let account: Option<(i32, String, bool, Option<String>, String)> = stmt
+        .query_row(params![username], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+        })
+        .optional()?;
+
+    let Some((id, password_hash, locked, locked_until, role)) = account else {
+        return Ok(None);
+    };
 
-    let count: i64 = stmt
-        .query_row(params![username, password], |row| row.get(0))
-        .map_err(|e| e.to_string())?;
+    if locked {
+        return Err(BmsError::unauthorized(format!(
+            "Account is locked after too many failed attempts. Try again after {} UTC.",
+            locked_until.unwrap_or_default()
+        )));
+    }
 
-    Ok(count > 0)
-}
\ No newline at end of file
+    Ok(Some(Account {
+        user: SessionUser {
+            id,
+            username: username.to_string(),
+            role: role.parse().map_err(BmsError::database)?,
+        },
+        password_hash,
+    }))
+}
+
+/// Maintains the lockout counters after a password check, storing
+/// `rehashed` in place of a legacy hash. Returns `None` for a wrong password.
+/// The account may have been locked by another attempt while the password was
+/// being checked; that lock stands either way.
+fn record_attempt(conn: &Connection, account: Account, check: PasswordCheck, rehashed: Option<String>) -> Result<Option<SessionUser>, BmsError> {
+    let id = account.user.id;
+
+    match check {
+        PasswordCheck::Valid | PasswordCheck::ValidLegacy => {
+            let changed = conn.execute_cached(
+                "UPDATE users SET password = COALESCE(?1, password), failed_attempts = 0, locked_until = NULL
+                 WHERE id = ?2 AND COALESCE(locked_until > datetime('now'), 0) = 0",
+                params![rehashed, id],
+            )?;
+            if changed == 0 {
+                return Err(BmsError::unauthorized("Account is locked after too many failed attempts"));
+            }
+            Ok(Some(account.user))
+        }
+        PasswordCheck::Invalid => {
+            // An expired lock starts the count over; an active one is left as is.
+            conn.execute_cached(
+                "UPDATE users SET
+                    failed_attempts = CASE
+                        WHEN locked_until IS NULL THEN failed_attempts + 1
+                        WHEN locked_until <= datetime('now') THEN 1
+                        ELSE failed_attempts
+                    END,
+                    locked_until = CASE
+                        WHEN locked_until > datetime('now') THEN locked_until
+                        WHEN CASE WHEN locked_until IS NULL THEN failed_attempts + 1 ELSE 1 END >= ?1
+                            THEN datetime('now', ?2)
+                        ELSE NULL
+                    END
+                 WHERE id = ?3",
+                params![MAX_FAILED_ATTEMPTS, LOCKOUT_DURATION, id],
+            )?;
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migration::migrate;
+
+    fn connection() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "foreign_keys", "ON").unwrap();
+        migrate(&mut conn).unwrap();
+        conn.execute("INSERT INTO users (username, password, role) VALUES ('juan', 'x', 'admin')", [])
+            .unwrap();
+        conn
+    }
+
+    fn fail(conn: &Connection, account: Account) {
+        assert!(record_attempt(conn, account, PasswordCheck::Invalid, None).unwrap().is_none());
+    }
+
+    fn counters(conn: &Connection) -> (i32, bool) {
+        conn.query_row(
+            "SELECT failed_attempts, COALESCE(locked_until > datetime('now'), 0) FROM users WHERE username = 'juan'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn locks_after_max_failed_attempts() {
+        let conn = connection();
+        for _ in 0..MAX_FAILED_ATTEMPTS {
+            fail(&conn, find_account(&conn, "juan").unwrap().unwrap());
+        }
+        assert_eq!(counters(&conn), (MAX_FAILED_ATTEMPTS, true));
+        assert!(find_account(&conn, "juan").is_err());
+    }
+
+    #[test]
+    fn late_attempts_keep_an_active_lock() {
+        let conn = connection();
+        // All of these passed the lock check before any was recorded.
+        let stale: Vec<Account> = (0..MAX_FAILED_ATTEMPTS + 2)
+            .map(|_| find_account(&conn, "juan").unwrap().unwrap())
+            .collect();
+        let mut stale = stale.into_iter();
+        for account in stale.by_ref().take(MAX_FAILED_ATTEMPTS as usize + 1) {
+            fail(&conn, account);
+        }
+        assert_eq!(counters(&conn), (MAX_FAILED_ATTEMPTS, true));
+
+        let correct = stale.next().unwrap();
+        assert!(record_attempt(&conn, correct, PasswordCheck::Valid, None).is_err());
+        assert_eq!(counters(&conn), (MAX_FAILED_ATTEMPTS, true));
+    }
+
+    #[test]
+    fn an_expired_lock_starts_the_count_over() {
+        let conn = connection();
+        conn.execute(
+            "UPDATE users SET failed_attempts = ?1, locked_until = datetime('now', '-1 minute')",
+            params![MAX_FAILED_ATTEMPTS],
+        )
+        .unwrap();
+        fail(&conn, find_account(&conn, "juan").unwrap().unwrap());
+        assert_eq!(counters(&conn), (1, false));
+
+        let account = find_account(&conn, "juan").unwrap().unwrap();
+        assert!(record_attempt(&conn, account, PasswordCheck::Valid, None).unwrap().is_some());
+        assert_eq!(counters(&conn), (0, false));
+    }
+}
@@ -0,0 +1,105 @@
+use crate::models::user::SessionUser;
+use rusqlite::{params, Connection, OptionalExtension, Result};
+use serde_json::{Map, Value};
+
+/// Columns holding base64 images. They are left out of snapshots so a photo
+/// upload does not copy megabytes into the audit log.
+const UNAUDITED_COLUMNS: &[&str] = &["photo", "image", "logo", "logo_municipality"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Create,
+    Update,
+    /// Moved to the recycle bin.
+    Delete,
+    /// Removed from the recycle bin for good.
+    Purge,
+}
+
+impl Operation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operation::Create => "create",
+            Operation::Update => "update",
+            Operation::Delete => "delete",
+            Operation::Purge => "purge",
+        }
+    }
+}
+
+/// Reads a row as a JSON object of column name to value, or `None` if it
+/// does not exist. `table` must be one of our own table names, never input.
+pub fn snapshot(conn: &Connection, table: &'static str, row_id: i64) -> Result<Option<Value>> {
+    let columns: Vec<String> = conn
+        .prepare_cached("SELECT name FROM pragma_table_info(?1)")?
+        .query_map(params![table], |row| row.get(0))?
+        .collect::<Result<_>>()?;
+
+    let pairs: Vec<String> = columns
+        .iter()
+        .filter(|c| !UNAUDITED_COLUMNS.contains(&c.as_str()))
+        .map(|c| format!("'{0}', \"{0}\"", c))
+        .collect();
+    let sql = format!(
+        "SELECT json_object({}) FROM {} WHERE id = ?1",
+        pairs.join(", "),
+        table
+    );
+
+    let json: Option<String> = conn
+        .prepare_cached(&sql)?
+        .query_row(params![row_id], |row| row.get(0))
+        .optional()?;
+
+    Ok(json.and_then(|j| serde_json::from_str(&j).ok()))
+}
+
+/// Writes an audit entry for a change that has just been applied. `before` is
+/// the snapshot taken prior to the change (`None` for creates); the row's
+/// current state, if it still exists, is read here. Only fields whose value
+/// changed are stored, as `{"field": {"before": .., "after": ..}}`.
+pub fn record_change(
+    conn: &Connection,
+    user: &SessionUser,
+    table: &'static str,
+    row_id: i64,
+    operation: Operation,
+    before: Option<Value>,
+) -> Result<()> {
+    let after = snapshot(conn, table, row_id)?;
+    let changes = diff(before.as_ref(), after.as_ref());
+
+    conn.prepare_cached(
+        "INSERT INTO audit_log (user_id, username, table_name, row_id, operation, changes)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?
+    .execute(params![
+        user.id,
+        user.username,
+        table,
+        row_id,
+        operation.as_str(),
+        Value::Object(changes).to_string()
+    ])?;
+
+    Ok(())
+}
+
+fn diff(before: Option<&Value>, after: Option<&Value>) -> Map<String, Value> {
+    let empty = Map::new();
+    let before = before.and_then(Value::as_object).unwrap_or(&empty);
+    let after = after.and_then(Value::as_object).unwrap_or(&empty);
+
+    let mut changes = Map::new();
+    for key in before.keys().chain(after.keys()) {
+        let old = before.get(key).cloned().unwrap_or(Value::Null);
+        let new = after.get(key).cloned().unwrap_or(Value::Null);
+        if old != new && !changes.contains_key(key) {
+            let mut change = Map::new();
+            change.insert("before".to_string(), old);
+            change.insert("after".to_string(), new);
+            changes.insert(key.clone(), Value::Object(change));
+        }
+    }
+    changes
+}
@@ -0,0 +1,179 @@
+use crate::models::blotter::{BlotterParty, CaseEvent, CaseEventKind, CaseStage, ResidentCase};
+use crate::models::user::SessionUser;
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, Result, Row};
+use std::str::FromStr;
+
+/// Reads a text column holding one of our enums, e.g. a party role.
+pub fn parse_column<T: FromStr<Err = String>>(row: &Row, index: usize) -> Result<T> {
+    let value: String = row.get(index)?;
+    value
+        .parse()
+        .map_err(|e: String| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, e.into()))
+}
+
+pub fn parse_optional_column<T: FromStr<Err = String>>(row: &Row, index: usize) -> Result<Option<T>> {
+    match row.get::<_, Option<String>>(index)? {
+        Some(_) => parse_column(row, index).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Reads the `stage` column of a blotter row.
+pub fn stage_column(row: &Row, index: usize) -> Result<CaseStage> {
+    parse_column(row, index)
+}
+
+/// Parties to a case in the order they were entered, complainants first.
+pub fn parties(conn: &Connection, blotter_id: i64) -> Result<Vec<BlotterParty>> {
+    conn.prepare_cached(
+        "SELECT id, role, resident_id, name, details FROM blotter_parties
+         WHERE blotter_id = ?1
+         ORDER BY CASE role WHEN 'complainant' THEN 0 WHEN 'respondent' THEN 1 ELSE 2 END, id",
+    )?
+    .query_map(params![blotter_id], |row| {
+        Ok(BlotterParty {
+            id: row.get(0)?,
+            role: parse_column(row, 1)?,
+            resident_id: row.get(2)?,
+            name: row.get(3)?,
+            details: row.get(4)?,
+        })
+    })?
+    .collect()
+}
+
+/// Saves the parties to a case. Parties sent back with their id are updated
+/// in place so their hearing attendance is kept; parties left out are removed.
+pub fn replace_parties(conn: &Connection, blotter_id: i64, parties: &[BlotterParty]) -> Result<()> {
+    let existing: Vec<i32> = conn
+        .prepare_cached("SELECT id FROM blotter_parties WHERE blotter_id = ?1")?
+        .query_map(params![blotter_id], |row| row.get(0))?
+        .collect::<Result<_>>()?;
+
+    let mut delete = conn.prepare_cached("DELETE FROM blotter_parties WHERE id = ?1")?;
+    for id in &existing {
+        if !parties.iter().any(|p| p.id == Some(*id)) {
+            delete.execute(params![id])?;
+        }
+    }
+
+    let mut update = conn.prepare_cached(
+        "UPDATE blotter_parties SET role = ?1, resident_id = ?2, name = ?3, details = ?4 WHERE id = ?5",
+    )?;
+    let mut insert = conn.prepare_cached(
+        "INSERT INTO blotter_parties (blotter_id, role, resident_id, name, details)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for party in parties {
+        match party.id.filter(|id| existing.contains(id)) {
+            Some(id) => update.execute(params![
+                party.role.as_str(),
+                party.resident_id,
+                party.name,
+                party.details,
+                id
+            ])?,
+            None => insert.execute(params![
+                blotter_id,
+                party.role.as_str(),
+                party.resident_id,
+                party.name,
+                party.details
+            ])?,
+        };
+    }
+
+    Ok(())
+}
+
+/// Every case a resident is party to, newest first.
+pub fn resident_cases(conn: &Connection, resident_id: i64) -> Result<Vec<ResidentCase>> {
+    conn.prepare_cached(
+        "SELECT b.id, p.role, b.type_, b.incident_date, b.stage, b.reported_by, b.involved
+         FROM blotter_parties p JOIN blotters b ON b.id = p.blotter_id
+         WHERE p.resident_id = ?1 AND b.deleted_at IS NULL
+         ORDER BY b.incident_date DESC, b.id DESC",
+    )?
+    .query_map(params![resident_id], |row| {
+        let stage: CaseStage = parse_column(row, 4)?;
+        Ok(ResidentCase {
+            blotter_id: row.get(0)?,
+            role: parse_column(row, 1)?,
+            type_: row.get(2)?,
+            incident_date: row.get(3)?,
+            stage,
+            status: stage.label().to_string(),
+            reported_by: row.get(5)?,
+            involved: row.get(6)?,
+            pending: !stage.is_closed(),
+        })
+    })?
+    .collect()
+}
+
+/// Appends a stage change to a case's history.
+pub fn record_transition(
+    conn: &Connection,
+    user: &SessionUser,
+    blotter_id: i64,
+    from: Option<CaseStage>,
+    to: CaseStage,
+    notes: &str,
+) -> Result<()> {
+    conn.prepare_cached(
+        "INSERT INTO blotter_case_events (blotter_id, kind, from_stage, to_stage, notes, user_id, username)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?
+    .execute(params![
+        blotter_id,
+        CaseEventKind::Transition.as_str(),
+        from.map(|s| s.as_str()),
+        to.as_str(),
+        notes,
+        user.id,
+        user.username
+    ])?;
+
+    Ok(())
+}
+
+/// Appends a hearing set for `hearing_date` to a case's history.
+pub fn record_hearing(conn: &Connection, user: &SessionUser, blotter_id: i64, hearing_date: &str, notes: &str) -> Result<()> {
+    conn.prepare_cached(
+        "INSERT INTO blotter_case_events (blotter_id, kind, hearing_date, notes, user_id, username)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?
+    .execute(params![
+        blotter_id,
+        CaseEventKind::Hearing.as_str(),
+        hearing_date,
+        notes,
+        user.id,
+        user.username
+    ])?;
+
+    Ok(())
+}
+
+/// A case's history, oldest first.
+pub fn case_events(conn: &Connection, blotter_id: i64) -> Result<Vec<CaseEvent>> {
+    conn.prepare_cached(
+        "SELECT id, blotter_id, kind, from_stage, to_stage, hearing_date, notes, occurred_at, username
+         FROM blotter_case_events WHERE blotter_id = ?1 ORDER BY id",
+    )?
+    .query_map(params![blotter_id], |row| {
+        Ok(CaseEvent {
+            id: row.get(0)?,
+            blotter_id: row.get(1)?,
+            kind: parse_column(row, 2)?,
+            from_stage: parse_optional_column(row, 3)?,
+            to_stage: parse_optional_column(row, 4)?,
+            hearing_date: row.get(5)?,
+            notes: row.get(6)?,
+            occurred_at: row.get(7)?,
+            username: row.get(8)?,
+        })
+    })?
+    .collect()
+}
@@ -0,0 +1,34 @@
+use crate::database::blotter::parse_column;
+use crate::models::budget::Appropriation;
+use crate::models::money::Money;
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+
+/// Appropriations with what has been obligated against them, leaving out
+/// the expense given as `?1` (NULL to count every expense).
+pub const APPROPRIATION_SELECT: &str = "SELECT a.id, a.fiscal_year, a.account_code, a.description, a.amount,
+        (SELECT COALESCE(SUM(e.amount), 0) FROM expenses e
+         WHERE e.appropriation_id = a.id AND e.deleted_at IS NULL AND e.id IS NOT ?1)
+    FROM appropriations a";
+
+pub fn appropriation_from_row(row: &Row) -> Result<Appropriation> {
+    let amount: Money = row.get(4)?;
+    let obligated: Money = row.get(5)?;
+
+    Ok(Appropriation {
+        id: row.get(0)?,
+        fiscal_year: row.get(1)?,
+        account_code: parse_column(row, 2)?,
+        description: row.get(3)?,
+        amount,
+        obligated,
+        remaining: amount - obligated,
+    })
+}
+
+/// An appropriation and its balance, not counting `except_expense` so an
+/// expense being edited is not charged twice.
+pub fn appropriation_balance(conn: &Connection, id: i64, except_expense: Option<i64>) -> Result<Option<Appropriation>> {
+    conn.prepare_cached(&format!("{} WHERE a.id = ?2", APPROPRIATION_SELECT))?
+        .query_row(params![except_expense, id], appropriation_from_row)
+        .optional()
+}
@@ -0,0 +1,209 @@
+use crate::error::BmsError;
+use crate::models::cashbook::Fund;
+use crate::models::money::Money;
+use rusqlite::{params, Connection, OptionalExtension, Result};
+
+/// Incomes that count as cash of the fund `?1`: not deleted or voided.
+pub const FUND_RECEIPTS: &str = "FROM incomes WHERE fund = ?1 AND deleted_at IS NULL AND voided_at IS NULL";
+
+pub const FUND_DISBURSEMENTS: &str = "FROM expenses WHERE fund = ?1 AND deleted_at IS NULL";
+
+pub const FUND_DEPOSITS: &str = "FROM bank_deposits WHERE fund = ?1";
+
+/// Entries from the fund's opening balance on; all of them if it has none.
+pub const SINCE_OPENING: &str = "date(date) >= COALESCE((SELECT as_of FROM fund_opening_balances WHERE fund = ?1), '')";
+
+/// The date the books are closed through, if they have ever been closed.
+pub fn closed_through(conn: &Connection) -> Result<Option<String>> {
+    conn.prepare_cached("SELECT MAX(closed_through) FROM cashbook_closings")?
+        .query_row([], |row| row.get(0))
+}
+
+/// Refuses changes to an entry dated `date` once the books are closed
+/// through that date.
+pub fn ensure_open(conn: &Connection, date: &str) -> std::result::Result<(), BmsError> {
+    let Some(closed) = closed_through(conn)? else {
+        return Ok(());
+    };
+
+    let locked: Option<bool> = conn
+        .prepare_cached("SELECT date(?1) <= date(?2)")?
+        .query_row(params![date, closed], |row| row.get(0))?;
+    if locked.unwrap_or(false) {
+        return Err(BmsError::conflict(format!(
+            "The books are closed through {}; entries dated up to then cannot be changed",
+            closed
+        )));
+    }
+    Ok(())
+}
+
+/// `ensure_open` for the stored date of an income, expense or deposit.
+pub fn ensure_row_open(conn: &Connection, table: &'static str, id: i64) -> std::result::Result<(), BmsError> {
+    let date: Option<String> = conn
+        .prepare_cached(&format!("SELECT date FROM {} WHERE id = ?1", table))?
+        .query_row(params![id], |row| row.get(0))
+        .optional()?;
+
+    match date {
+        Some(date) => ensure_open(conn, &date),
+        None => Ok(()),
+    }
+}
+
+/// Sum of the amounts `from` selects for `fund`, from its opening balance
+/// through the end of `through`.
+fn total_through(conn: &Connection, from: &str, fund: Fund, through: &str) -> Result<Money> {
+    conn.prepare_cached(&format!(
+        "SELECT COALESCE(SUM(amount), 0) {} AND {} AND date(date) <= date(?2)",
+        from, SINCE_OPENING
+    ))?
+    .query_row(params![fund.as_str(), through], |row| row.get(0))
+}
+
+pub fn receipts_through(conn: &Connection, fund: Fund, through: &str) -> Result<Money> {
+    total_through(conn, FUND_RECEIPTS, fund, through)
+}
+
+pub fn deposits_through(conn: &Connection, fund: Fund, through: &str) -> Result<Money> {
+    total_through(conn, FUND_DEPOSITS, fund, through)
+}
+
+/// The fund's balance at the end of `through`.
+pub fn balance_through(conn: &Connection, fund: Fund, through: &str) -> Result<Money> {
+    let opening: Money = conn
+        .prepare_cached("SELECT amount FROM fund_opening_balances WHERE fund = ?1")?
+        .query_row(params![fund.as_str()], |row| row.get(0))
+        .optional()?
+        .unwrap_or_default();
+
+    Ok(opening + receipts_through(conn, fund, through)? - total_through(conn, FUND_DISBURSEMENTS, fund, through)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migration::migrate;
+
+    fn connection() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "foreign_keys", "ON").unwrap();
+        migrate(&mut conn).unwrap();
+        conn
+    }
+
+    fn close(conn: &Connection, through: &str) {
+        conn.execute(
+            "INSERT INTO cashbook_closings (closed_through, user_id, username) VALUES (?1, 1, 'treasurer')",
+            params![through],
+        )
+        .unwrap();
+    }
+
+    /// What `reopen_cashbook_period_command` does: drops the latest closing.
+    fn reopen(conn: &Connection) {
+        conn.execute(
+            "DELETE FROM cashbook_closings
+             WHERE id = (SELECT id FROM cashbook_closings ORDER BY closed_through DESC, id DESC LIMIT 1)",
+            [],
+        )
+        .unwrap();
+    }
+
+    fn income(conn: &Connection, or_number: i32, fund: Fund, date: &str, centavos: i64, state: &str) {
+        conn.execute(
+            &format!(
+                "INSERT INTO incomes (type_, amount, or_number, received_from, received_by, category, date, fund, voided_at, deleted_at)
+                 VALUES ('Fees', ?1, ?2, 'Juan Cruz', 'treasurer', 'Fees', ?3, ?4, {})",
+                match state {
+                    "voided" => "datetime('now'), NULL",
+                    "deleted" => "NULL, datetime('now')",
+                    _ => "NULL, NULL",
+                }
+            ),
+            params![centavos, or_number, date, fund.as_str()],
+        )
+        .unwrap();
+    }
+
+    fn expense(conn: &Connection, fund: Fund, date: &str, centavos: i64, deleted: bool) {
+        conn.execute(
+            "INSERT INTO expenses (type_, amount, paid_to, paid_by, date, category, or_number, fund, deleted_at)
+             VALUES ('Supplies', ?1, 'Store', 'treasurer', ?2, 'Office', 1, ?3, CASE WHEN ?4 THEN datetime('now') END)",
+            params![centavos, date, fund.as_str(), deleted],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn closing_locks_entries_through_its_date() {
+        let conn = connection();
+        assert!(ensure_open(&conn, "2026-01-15").is_ok());
+
+        close(&conn, "2026-01-31");
+        assert!(ensure_open(&conn, "2026-01-15").is_err());
+        assert!(ensure_open(&conn, "2026-01-31").is_err());
+        assert!(ensure_open(&conn, "2026-01-31 17:00").is_err());
+        assert!(ensure_open(&conn, "2026-02-01").is_ok());
+        assert_eq!(closed_through(&conn).unwrap().as_deref(), Some("2026-01-31"));
+    }
+
+    #[test]
+    fn reopening_unlocks_only_the_latest_closing() {
+        let conn = connection();
+        close(&conn, "2026-01-31");
+        close(&conn, "2026-02-28");
+        assert!(ensure_open(&conn, "2026-02-15").is_err());
+
+        reopen(&conn);
+        assert!(ensure_open(&conn, "2026-02-15").is_ok());
+        assert!(ensure_open(&conn, "2026-01-15").is_err());
+
+        reopen(&conn);
+        assert!(ensure_open(&conn, "2026-01-15").is_ok());
+        assert_eq!(closed_through(&conn).unwrap(), None);
+    }
+
+    #[test]
+    fn stored_rows_are_checked_by_their_date() {
+        let conn = connection();
+        income(&conn, 1, Fund::General, "2026-01-10", 5_000, "");
+        close(&conn, "2026-01-31");
+        assert!(ensure_row_open(&conn, "incomes", 1).is_err());
+        assert!(ensure_row_open(&conn, "incomes", 99).is_ok());
+    }
+
+    #[test]
+    fn balances_run_from_the_opening_balance() {
+        let conn = connection();
+        conn.execute(
+            "INSERT INTO fund_opening_balances (fund, as_of, amount) VALUES ('general_fund', '2026-01-01', 100000)",
+            [],
+        )
+        .unwrap();
+        income(&conn, 1, Fund::General, "2025-12-31", 5_000, "");
+        income(&conn, 2, Fund::General, "2026-01-05", 20_000, "");
+        income(&conn, 3, Fund::General, "2026-01-06", 30_000, "voided");
+        income(&conn, 4, Fund::General, "2026-01-07", 40_000, "deleted");
+        income(&conn, 5, Fund::Sk, "2026-01-05", 7_000, "");
+        expense(&conn, Fund::General, "2026-01-10", 15_000, false);
+        expense(&conn, Fund::General, "2026-01-11", 90_000, true);
+        conn.execute(
+            "INSERT INTO bank_deposits (fund, date, amount) VALUES ('general_fund', '2026-01-08', 10000)",
+            [],
+        )
+        .unwrap();
+
+        let balance = |fund, through| balance_through(&conn, fund, through).unwrap().centavos();
+        assert_eq!(balance(Fund::General, "2026-01-04"), 100_000);
+        assert_eq!(balance(Fund::General, "2026-01-05"), 120_000);
+        assert_eq!(balance(Fund::General, "2026-01-10"), 105_000);
+        assert_eq!(balance(Fund::General, "2026-12-31"), 105_000);
+        assert_eq!(balance(Fund::Sk, "2026-01-31"), 7_000);
+        assert_eq!(balance(Fund::Trust, "2026-01-31"), 0);
+
+        assert_eq!(receipts_through(&conn, Fund::General, "2026-01-31").unwrap().centavos(), 20_000);
+        assert_eq!(deposits_through(&conn, Fund::General, "2026-01-07").unwrap().centavos(), 0);
+        assert_eq!(deposits_through(&conn, Fund::General, "2026-01-08").unwrap().centavos(), 10_000);
+    }
+}
@@ -0,0 +1,245 @@
+use crate::error::BmsError;
+use crate::models::certificate::{type_code, CertificatePayload, VerificationStatus};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier};
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OptionalExtension, Result};
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// Prefix of a signed certificate payload; bumped if the format changes.
+const PAYLOAD_PREFIX: &str = "BMS1";
+
+/// Name of the signing key file, kept in the database's directory.
+const KEY_FILE: &str = "certificate-signing.key";
+
+/// Takes the next control number for a certificate of `type_` issued on
+/// `issued_date` (today if unset), e.g. `2026-CLR-000123`. Numbers run from 1
+/// per type and year. The counter is bumped in the caller's transaction, so a
+/// certificate that fails to save gives its number back.
+pub fn next_control_number(conn: &Connection, type_: &str, issued_date: Option<&str>) -> Result<String> {
+    let code = type_code(type_);
+    let year: i64 = conn
+        .prepare_cached("SELECT CAST(COALESCE(strftime('%Y', ?1), strftime('%Y', 'now')) AS INTEGER)")?
+        .query_row(params![issued_date], |row| row.get(0))?;
+
+    let number: i64 = conn
+        .prepare_cached(
+            "INSERT INTO certificate_sequences (type_code, year, last_number) VALUES (?1, ?2, 1)
+             ON CONFLICT (type_code, year) DO UPDATE SET last_number = last_number + 1
+             RETURNING last_number",
+        )?
+        .query_row(params![code, year], |row| row.get(0))?;
+
+    Ok(format!("{}-{}-{:06}", year, code, number))
+}
+
+/// This installation's certificate signing key, or `None` before the first
+/// certificate is signed. It is kept in `certificate-signing.key` beside the
+/// database rather than in it, readable only by the current user, so a copy
+/// of bms.db alone cannot be used to sign certificates; backups must take the
+/// two files together. A key still stored in the database from before is
+/// moved out to the file. An in-memory database has no directory and keeps
+/// its key in the table.
+///
+/// Fails if the key is gone while certificates signed with it exist, as after
+/// restoring the database without its key file: a new key would make every
+/// certificate already printed fail verification.
+pub fn signing_key(conn: &Connection) -> std::result::Result<Option<SigningKey>, BmsError> {
+    let stored: Option<Vec<u8>> = conn
+        .prepare_cached("SELECT secret_key FROM certificate_signing_key WHERE id = 1")?
+        .query_row([], |row| row.get(0))
+        .optional()?;
+
+    let key = match key_path(conn) {
+        None => stored.map(key_from_bytes).transpose()?,
+        Some(path) => match fs::read(&path) {
+            Ok(bytes) => Some(key_from_bytes(bytes)?),
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+            Err(_) => match stored {
+                Some(bytes) => {
+                    let key = key_from_bytes(bytes)?;
+                    write_key_file(&path, &key)?;
+                    conn.pragma_update(None, "secure_delete", true)?;
+                    conn.prepare_cached("DELETE FROM certificate_signing_key")?.execute([])?;
+                    Some(key)
+                }
+                None => None,
+            },
+        },
+    };
+
+    if key.is_none() {
+        let signed: bool = conn
+            .prepare_cached("SELECT EXISTS (SELECT 1 FROM certificates WHERE signed_payload IS NOT NULL)")?
+            .query_row([], |row| row.get(0))?;
+        if signed {
+            return Err(BmsError::Io {
+                message: format!(
+                    "The certificate signing key ({}) is missing from the folder holding the database. \
+                     Restore it from the backup taken with the database; certificates cannot be signed or verified until then.",
+                    KEY_FILE
+                ),
+            });
+        }
+    }
+    Ok(key)
+}
+
+/// The key to sign with, made the first time a certificate is signed.
+fn key_for_signing(conn: &Connection) -> std::result::Result<SigningKey, BmsError> {
+    if let Some(key) = signing_key(conn)? {
+        return Ok(key);
+    }
+
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    let key = SigningKey::from_bytes(&bytes);
+    match key_path(conn) {
+        Some(path) => write_key_file(&path, &key)?,
+        None => {
+            conn.prepare_cached(
+                "INSERT INTO certificate_signing_key (id, secret_key, public_key) VALUES (1, ?1, ?2)",
+            )?
+            .execute(params![key.to_bytes().as_slice(), key.verifying_key().to_bytes().as_slice()])?;
+        }
+    }
+    Ok(key)
+}
+
+/// Where the key file goes: beside the database, or nowhere for an in-memory one.
+fn key_path(conn: &Connection) -> Option<PathBuf> {
+    conn.path()
+        .filter(|path| !path.is_empty())
+        .map(|path| Path::new(path).with_file_name(KEY_FILE))
+}
+
+fn key_from_bytes(bytes: Vec<u8>) -> Result<SigningKey> {
+    let bytes: [u8; 32] = bytes.try_into().map_err(|_| {
+        rusqlite::Error::FromSqlConversionFailure(0, Type::Blob, "signing key is not 32 bytes".into())
+    })?;
+    Ok(SigningKey::from_bytes(&bytes))
+}
+
+/// Creates the key file, failing if one already exists. On Windows the file
+/// is protected by the per-user ACL of the app data directory instead.
+fn write_key_file(path: &Path, key: &SigningKey) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut file = options.open(path)?;
+    file.write_all(&key.to_bytes())?;
+    file.sync_all()
+}
+
+/// Signs the certificate's current details and stores the result as its QR
+/// payload: `BMS1.<details>.<signature>`, both parts base64url.
+pub fn sign_certificate(conn: &Connection, certificate_id: i64) -> std::result::Result<String, BmsError> {
+    let payload = conn
+        .prepare_cached(
+            "SELECT control_number, resident_id, resident_name, type_, issued_date FROM certificates WHERE id = ?1",
+        )?
+        .query_row(params![certificate_id], |row| {
+            Ok(CertificatePayload {
+                control_number: row.get(0)?,
+                resident_id: row.get(1)?,
+                resident_name: row.get(2)?,
+                type_: row.get(3)?,
+                issued_date: row.get(4)?,
+            })
+        })?;
+
+    let details = serde_json::to_vec(&payload).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    let signature = key_for_signing(conn)?.sign(&details);
+    let signed = format!(
+        "{}.{}.{}",
+        PAYLOAD_PREFIX,
+        URL_SAFE_NO_PAD.encode(&details),
+        URL_SAFE_NO_PAD.encode(signature.to_bytes())
+    );
+
+    conn.prepare_cached("UPDATE certificates SET signed_payload = ?1 WHERE id = ?2")?
+        .execute(params![signed, certificate_id])?;
+    Ok(signed)
+}
+
+/// The details in a signed payload if it was signed with `key`.
+pub fn open_payload(key: &SigningKey, signed: &str) -> std::result::Result<CertificatePayload, VerificationStatus> {
+    let mut parts = signed.trim().split('.');
+    let (Some(PAYLOAD_PREFIX), Some(details), Some(signature), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+        return Err(VerificationStatus::Malformed);
+    };
+    let (Ok(details), Ok(signature)) = (URL_SAFE_NO_PAD.decode(details), URL_SAFE_NO_PAD.decode(signature)) else {
+        return Err(VerificationStatus::Malformed);
+    };
+
+    let signature = Signature::from_slice(&signature).map_err(|_| VerificationStatus::Malformed)?;
+    key.verifying_key()
+        .verify(&details, &signature)
+        .map_err(|_| VerificationStatus::BadSignature)?;
+    serde_json::from_slice(&details).map_err(|_| VerificationStatus::Malformed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migration::migrate;
+
+    fn connection() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "foreign_keys", "ON").unwrap();
+        migrate(&mut conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn control_numbers_run_per_type_and_year() {
+        let conn = connection();
+        let next = |type_: &str, date: &str| next_control_number(&conn, type_, Some(date)).unwrap();
+        assert_eq!(next("Barangay Clearance", "2025-12-31"), "2025-CLR-000001");
+        assert_eq!(next("Barangay Clearance", "2026-01-01"), "2026-CLR-000001");
+        assert_eq!(next("barangay clearance", "2026-06-30"), "2026-CLR-000002");
+        assert_eq!(next("Indigency Certificate", "2026-06-30"), "2026-IND-000001");
+        assert_eq!(next("Barangay Clearance", "2025-12-31T23:59"), "2025-CLR-000002");
+    }
+
+    #[test]
+    fn an_unset_issue_date_numbers_in_the_current_year() {
+        let conn = connection();
+        let year: String = conn.query_row("SELECT strftime('%Y', 'now')", [], |row| row.get(0)).unwrap();
+        assert_eq!(
+            next_control_number(&conn, "Barangay Clearance", None).unwrap(),
+            format!("{}-CLR-000001", year)
+        );
+    }
+
+    #[test]
+    fn a_rolled_back_certificate_gives_its_number_back() {
+        let conn = connection();
+        {
+            let tx = conn.unchecked_transaction().unwrap();
+            next_control_number(&tx, "Barangay Clearance", Some("2026-01-05")).unwrap();
+        }
+        assert_eq!(
+            next_control_number(&conn, "Barangay Clearance", Some("2026-01-05")).unwrap(),
+            "2026-CLR-000001"
+        );
+    }
+
+    #[test]
+    fn a_lost_key_is_not_replaced_once_certificates_are_signed() {
+        let conn = connection();
+        assert!(signing_key(&conn).unwrap().is_none());
+        conn.execute(
+            "INSERT INTO certificates (resident_name, type_, signed_payload) VALUES ('Juan Cruz', 'Residency', 'BMS1.a.b')",
+            [],
+        )
+        .unwrap();
+        assert!(signing_key(&conn).is_err());
+        assert!(sign_certificate(&conn, 1).is_err());
+    }
+}
@@ -1,7 +1,13 @@
+use crate::error::BmsError;
 use dirs_next::data_local_dir;
-use rusqlite::{Connection, Result};
+use rusqlite::{Connection, Params, Result};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
 use std::{fs, path::PathBuf};
 
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+const STATEMENT_CACHE_CAPACITY: usize = 64;
+
 pub fn establish_connection() -> Result<Connection> {
     let db_dir: PathBuf = data_local_dir()
         .expect("Failed to get app data dir")
@@ -11,7 +17,45 @@ pub fn establish_connection() -> Result<Connection> {
     let db_path = db_dir.join("bms.db");
     let conn = Connection::open(db_path)?;
 
-    conn.pragma_update(None, "journal_mode", &"WAL")?;
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+    conn.pragma_update(None, "foreign_keys", "ON")?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
 
     Ok(conn)
 }
+
+/// The application's single SQLite connection, registered with
+/// `tauri::Builder::manage` and injected into commands as `State<DbPool>`.
+/// Commands take turns on the connection instead of each opening their own,
+/// so concurrent invokes queue up rather than failing with "database is locked".
+pub struct DbPool {
+    conn: Mutex<Connection>,
+}
+
+impl DbPool {
+    pub fn new(conn: Connection) -> Self {
+        DbPool {
+            conn: Mutex::new(conn),
+        }
+    }
+
+    /// A command that panicked while holding the connection leaves the lock
+    /// poisoned. Its transaction was rolled back as the panic unwound, so the
+    /// connection is taken over rather than refused to every later command.
+    pub fn get(&self) -> Result<MutexGuard<'_, Connection>, BmsError> {
+        Ok(self.conn.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+/// `Connection::execute` re-prepares its SQL on every call; this goes through
+/// the connection's statement cache instead.
+pub trait ExecuteCached {
+    fn execute_cached<P: Params>(&self, sql: &str, params: P) -> Result<usize>;
+}
+
+impl ExecuteCached for Connection {
+    fn execute_cached<P: Params>(&self, sql: &str, params: P) -> Result<usize> {
+        self.prepare_cached(sql)?.execute(params)
+    }
+}
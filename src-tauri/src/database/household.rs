@@ -0,0 +1,53 @@
+use crate::models::household::HouseholdMember;
+use rusqlite::{params, Connection, Result};
+
+/// A resident's display name, e.g. "Juan Santos Dela Cruz Jr.", for a query
+/// where the residents table is aliased `r`.
+pub const RESIDENT_NAME_SQL: &str = "r.first_name || COALESCE(' ' || NULLIF(r.middle_name, ''), '') || ' ' || r.last_name || COALESCE(' ' || NULLIF(r.suffix, ''), '')";
+
+/// Relationship recorded for the head of the household.
+pub const HEAD_RELATIONSHIP: &str = "Head";
+
+/// Members of a household who have not been deleted, head first.
+pub fn members(conn: &Connection, household_id: i64) -> Result<Vec<HouseholdMember>> {
+    conn.prepare_cached(&format!(
+        "SELECT m.resident_id, {}, m.relationship
+         FROM household_members m JOIN residents r ON r.id = m.resident_id
+         WHERE m.household_id = ?1 AND r.deleted_at IS NULL
+         ORDER BY m.relationship <> ?2, r.last_name, r.first_name",
+        RESIDENT_NAME_SQL
+    ))?
+    .query_map(params![household_id, HEAD_RELATIONSHIP], |row| {
+        Ok(HouseholdMember {
+            resident_id: row.get(0)?,
+            name: row.get(1)?,
+            relationship: row.get(2)?,
+        })
+    })?
+    .collect()
+}
+
+/// Replaces the member list of a household. The head is always kept as a
+/// member with the "Head" relationship.
+pub fn replace_members(
+    conn: &Connection,
+    household_id: i64,
+    head_resident_id: Option<i32>,
+    members: &[HouseholdMember],
+) -> Result<()> {
+    conn.prepare_cached("DELETE FROM household_members WHERE household_id = ?1")?
+        .execute(params![household_id])?;
+
+    let mut insert = conn.prepare_cached(
+        "INSERT OR IGNORE INTO household_members (household_id, resident_id, relationship)
+         VALUES (?1, ?2, ?3)",
+    )?;
+    if let Some(head) = head_resident_id {
+        insert.execute(params![household_id, head, HEAD_RELATIONSHIP])?;
+    }
+    for member in members {
+        insert.execute(params![household_id, member.resident_id, member.relationship])?;
+    }
+
+    Ok(())
+}
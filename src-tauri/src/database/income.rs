@@ -0,0 +1,113 @@
+use crate::models::income::Income;
+use rusqlite::{params, Connection, OptionalExtension, Result};
+
+/// Receipt numbers taken in each booklet, by incomes (voided and deleted
+/// ones included) or by cancelling the blank receipt.
+pub const BOOKLET_USAGE: &str = "WITH used (booklet_id, or_number) AS (
+        SELECT booklet_id, or_number FROM incomes WHERE booklet_id IS NOT NULL
+        UNION
+        SELECT booklet_id, or_number FROM cancelled_receipts
+    )";
+
+/// An official receipt number and the booklet it comes from, if any.
+#[derive(Debug, Clone, Copy)]
+pub struct ReceiptNumber {
+    pub or_number: i32,
+    pub booklet_id: Option<i64>,
+}
+
+/// Whether any OR booklets have been registered. Until one is, receipt
+/// numbers are not tied to booklets.
+pub fn booklets_registered(conn: &Connection) -> Result<bool> {
+    conn.prepare_cached("SELECT EXISTS (SELECT 1 FROM or_booklets)")?
+        .query_row([], |row| row.get(0))
+}
+
+/// The next receipt for `collector_id`: the number after the last one taken
+/// in the first booklet assigned to them that has numbers left, falling back
+/// to unassigned booklets. Without any booklets it is the number after the
+/// highest one ever used. None when booklets are registered but none has a
+/// number left for this collector.
+pub fn next_or_number(conn: &Connection, collector_id: i32) -> Result<Option<ReceiptNumber>> {
+    if !booklets_registered(conn)? {
+        let or_number = conn
+            .prepare_cached("SELECT COALESCE(MAX(or_number), 0) + 1 FROM incomes")?
+            .query_row([], |row| row.get(0))?;
+        return Ok(Some(ReceiptNumber { or_number, booklet_id: None }));
+    }
+
+    let next = conn
+        .prepare_cached(&format!(
+            "{}
+            SELECT b.id, COALESCE(MAX(used.or_number) + 1, b.series_start) AS next
+            FROM or_booklets b
+            LEFT JOIN used ON used.booklet_id = b.id
+            WHERE b.collector_id = ?1 OR b.collector_id IS NULL
+            GROUP BY b.id
+            HAVING next <= b.series_end
+            ORDER BY b.collector_id IS NULL, b.series_start
+            LIMIT 1",
+            BOOKLET_USAGE
+        ))?
+        .query_row(params![collector_id], |row| {
+            Ok(ReceiptNumber {
+                or_number: row.get(1)?,
+                booklet_id: Some(row.get(0)?),
+            })
+        })
+        .optional()?;
+
+    Ok(next)
+}
+
+/// The booklet whose series contains `or_number`.
+pub fn booklet_of(conn: &Connection, or_number: i32) -> Result<Option<i64>> {
+    conn.prepare_cached("SELECT id FROM or_booklets WHERE ?1 BETWEEN series_start AND series_end")?
+        .query_row(params![or_number], |row| row.get(0))
+        .optional()
+}
+
+/// Whether `or_number` is already on an income other than `except_id`, or
+/// was cancelled.
+pub fn or_number_taken(conn: &Connection, or_number: i32, except_id: Option<i64>) -> Result<bool> {
+    conn.prepare_cached(
+        "SELECT EXISTS (SELECT 1 FROM incomes WHERE or_number = ?1 AND id IS NOT ?2)
+             OR EXISTS (SELECT 1 FROM cancelled_receipts WHERE or_number = ?1)",
+    )?
+    .query_row(params![or_number, except_id], |row| row.get(0))
+}
+
+/// Inserts an income under `booklet_id` and returns its id. The caller
+/// records the audit entry.
+pub fn insert_income(conn: &Connection, income: &Income, booklet_id: Option<i64>) -> Result<i64> {
+    conn.prepare_cached(
+        "INSERT INTO incomes (type_, amount, or_number, received_from, received_by, category, date, fund, booklet_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+    )?
+    .execute(params![
+        income.type_,
+        income.amount,
+        income.or_number,
+        income.received_from,
+        income.received_by,
+        income.category,
+        income.date,
+        income.fund.as_str(),
+        booklet_id,
+    ])?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+/// Marks an income void. It stays on record under its receipt number but no
+/// longer counts as money received. Returns false if it was already void.
+pub fn void_income(conn: &Connection, id: i64, reason: &str) -> Result<bool> {
+    let changed = conn
+        .prepare_cached(
+            "UPDATE incomes SET voided_at = datetime('now'), void_reason = ?1
+             WHERE id = ?2 AND voided_at IS NULL",
+        )?
+        .execute(params![reason, id])?;
+
+    Ok(changed > 0)
+}
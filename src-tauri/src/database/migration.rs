@@ -1,7 +1,231 @@
-use rusqlite::{Connection, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ed25519_dalek::{Signer, SigningKey};
+use rusqlite::types::{Type, Value};
+use rusqlite::{params, Connection, OptionalExtension, Transaction, TransactionBehavior};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
 
-pub fn migrate(conn: &Connection) -> Result<()> {
+/// A numbered schema change. Versions start at 1 and must stay contiguous;
+/// never edit a migration that has shipped, append a new one instead.
+/// Migrations do not call application code, which is free to change; what
+/// they need is copied into this module as it was when they shipped.
+struct Migration {
+    version: i64,
+    description: &'static str,
+    up: fn(&Transaction) -> rusqlite::Result<()>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial schema",
+        up: v1_initial_schema,
+    },
+    Migration {
+        version: 2,
+        description: "users: failed login tracking and lockout",
+        up: v2_user_lockout,
+    },
+    Migration {
+        version: 3,
+        description: "users: roles",
+        up: v3_user_roles,
+    },
+    Migration {
+        version: 4,
+        description: "audit log",
+        up: v4_audit_log,
+    },
+    Migration {
+        version: 5,
+        description: "soft delete columns",
+        up: v5_soft_delete,
+    },
+    Migration {
+        version: 6,
+        description: "indexes for paged queries",
+        up: v6_query_indexes,
+    },
+    Migration {
+        version: 7,
+        description: "full-text search over residents, blotters and certificates",
+        up: v7_search_index,
+    },
+    Migration {
+        version: 8,
+        description: "household members linked to residents",
+        up: v8_household_members,
+    },
+    Migration {
+        version: 9,
+        description: "certificates linked to residents",
+        up: v9_certificate_residents,
+    },
+    Migration {
+        version: 10,
+        description: "structured blotter parties",
+        up: v10_blotter_parties,
+    },
+    Migration {
+        version: 11,
+        description: "blotter case stages and history",
+        up: v11_blotter_case_stages,
+    },
+    Migration {
+        version: 12,
+        description: "blotter hearing sessions and attendance",
+        up: v12_blotter_hearings,
+    },
+    Migration {
+        version: 13,
+        description: "certificates: clearance override reason",
+        up: v13_clearance_override,
+    },
+    Migration {
+        version: 14,
+        description: "certificates: control numbers and print log",
+        up: v14_certificate_control_numbers,
+    },
+    Migration {
+        version: 15,
+        description: "certificates: signed QR payloads",
+        up: v15_certificate_signatures,
+    },
+    Migration {
+        version: 16,
+        description: "certificate fees linked to incomes",
+        up: v16_certificate_fees,
+    },
+    Migration {
+        version: 17,
+        description: "incomes: OR booklets and unique receipt numbers",
+        up: v17_or_booklets,
+    },
+    Migration {
+        version: 18,
+        description: "amounts stored as whole centavos",
+        up: v18_money_centavos,
+    },
+    Migration {
+        version: 19,
+        description: "budget appropriations charged by expenses",
+        up: v19_appropriations,
+    },
+    Migration {
+        version: 20,
+        description: "cashbook: funds, closings and bank deposits",
+        up: v20_cashbook,
+    },
+    Migration {
+        version: 21,
+        description: "audit_log: purge operation",
+        up: v21_audit_purge,
+    },
+    Migration {
+        version: 22,
+        description: "certificates: fee override reason",
+        up: v22_certificate_fee_override,
+    },
+    Migration {
+        version: 23,
+        description: "incomes: duplicate links cleared on delete",
+        up: v23_income_duplicate_links,
+    },
+    Migration {
+        version: 24,
+        description: "residents, households, blotters: zones trimmed",
+        up: v24_trim_zones,
+    },
+    Migration {
+        version: 25,
+        description: "certificate_fees: row ids for the audit log",
+        up: v25_certificate_fee_ids,
+    },
+    Migration {
+        version: 26,
+        description: "certificates: ages that are not whole numbers cleared",
+        up: v26_certificate_ages,
+    },
+];
+
+/// Highest schema version this binary knows how to handle.
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+#[derive(Debug)]
+pub enum MigrationError {
+    Sqlite(rusqlite::Error),
+    DatabaseTooNew { found: i64, supported: i64 },
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::Sqlite(e) => write!(f, "{}", e),
+            MigrationError::DatabaseTooNew { found, supported } => write!(
+                f,
+                "database schema version {} is newer than this app supports ({}); please update BMS",
+                found, supported
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+impl From<rusqlite::Error> for MigrationError {
+    fn from(e: rusqlite::Error) -> Self {
+        MigrationError::Sqlite(e)
+    }
+}
+
+/// Brings the database up to `latest_version()`. Each pending migration runs
+/// in its own transaction together with its `schema_migrations` record, so a
+/// failure leaves the database at the last fully applied version. Returns the
+/// versions applied, oldest first.
+pub fn migrate(conn: &mut Connection) -> Result<Vec<i64>, MigrationError> {
     conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );",
+    )?;
+
+    let current = current_version(conn)?;
+    let supported = latest_version();
+    if current > supported {
+        return Err(MigrationError::DatabaseTooNew { found: current, supported });
+    }
+
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        (migration.up)(&tx)?;
+        tx.execute(
+            "INSERT INTO schema_migrations (version, description) VALUES (?1, ?2)",
+            (migration.version, migration.description),
+        )?;
+        tx.commit()?;
+        applied.push(migration.version);
+    }
+
+    Ok(applied)
+}
+
+fn current_version(conn: &Connection) -> rusqlite::Result<i64> {
+    let version: Option<i64> =
+        conn.query_row("SELECT MAX(version) FROM schema_migrations", [], |row| row.get(0))?;
+    Ok(version.unwrap_or(0))
+}
+
+// Uses IF NOT EXISTS so that installs created before versioning was
+// introduced are adopted as version 1 without touching their data.
+fn v1_initial_schema(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
         "
         CREATE TABLE IF NOT EXISTS blotters (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -146,6 +370,1103 @@ pub fn migrate(conn: &Connection) -> Result<()> {
             password TEXT NOT NULL
         );
         ",
+    )
+}
+
+// Existing plaintext passwords are left in place and rehashed on the user's
+// next successful login.
+fn v2_user_lockout(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        ALTER TABLE users ADD COLUMN failed_attempts INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE users ADD COLUMN locked_until TEXT;
+        ",
+    )
+}
+
+// Accounts that predate roles had unrestricted access, so they become admins
+// rather than being locked out; new accounts default to read-only.
+fn v3_user_roles(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        ALTER TABLE users ADD COLUMN role TEXT NOT NULL DEFAULT 'viewer'
+            CHECK (role IN ('admin', 'secretary', 'treasurer', 'kagawad', 'viewer'));
+        UPDATE users SET role = 'admin';
+        ",
+    )
+}
+
+fn v4_audit_log(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            username TEXT NOT NULL,
+            occurred_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            table_name TEXT NOT NULL,
+            row_id INTEGER NOT NULL,
+            operation TEXT NOT NULL CHECK (operation IN ('create', 'update', 'delete')),
+            changes TEXT NOT NULL
+        );
+        CREATE INDEX idx_audit_log_table_row ON audit_log (table_name, row_id);
+        CREATE INDEX idx_audit_log_occurred_at ON audit_log (occurred_at);
+        ",
+    )
+}
+
+fn v5_soft_delete(tx: &Transaction) -> rusqlite::Result<()> {
+    let tables = [
+        "residents",
+        "blotters",
+        "certificates",
+        "incomes",
+        "expenses",
+        "households",
+        "officials",
+        "events",
+        "logbook",
+    ];
+    for table in tables {
+        tx.execute_batch(&format!(
+            "
+            ALTER TABLE {0} ADD COLUMN deleted_at TEXT;
+            ALTER TABLE {0} ADD COLUMN deleted_by INTEGER;
+            CREATE INDEX idx_{0}_deleted_at ON {0} (deleted_at);
+            ",
+            table
+        ))?;
+    }
+    Ok(())
+}
+
+fn v6_query_indexes(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE INDEX idx_residents_last_name ON residents (last_name, first_name);
+        CREATE INDEX idx_residents_zone ON residents (zone);
+        CREATE INDEX idx_blotters_incident_date ON blotters (incident_date);
+        CREATE INDEX idx_certificates_issued_date ON certificates (issued_date);
+        CREATE INDEX idx_incomes_date ON incomes (date);
+        CREATE INDEX idx_logbook_date ON logbook (date);
+        ",
+    )
+}
+
+// One FTS5 table per searchable entity, keyed by the entity's id as rowid and
+// kept in sync by triggers. Soft-deleted rows are dropped from the index.
+fn v7_search_index(tx: &Transaction) -> rusqlite::Result<()> {
+    let indexes = [
+        (
+            "residents",
+            "trim(first_name || ' ' || COALESCE(middle_name, '') || ' ' || last_name || ' ' || COALESCE(suffix, ''))",
+            "father_first_name || ' ' || father_middle_name || ' ' || father_last_name || ' ' || mother_first_name || ' ' || mother_middle_name || ' ' || mother_last_name",
+        ),
+        (
+            "blotters",
+            "reported_by || ' vs ' || involved",
+            "type_ || ' ' || witnesses || ' ' || narrative",
+        ),
+        ("certificates", "resident_name", "type_"),
+    ];
+
+    for (table, title, body) in indexes {
+        tx.execute_batch(&format!(
+            "
+            CREATE VIRTUAL TABLE {table}_fts USING fts5(
+                title, body, tokenize = 'unicode61 remove_diacritics 2'
+            );
+            INSERT INTO {table}_fts (rowid, title, body)
+                SELECT id, {title}, {body} FROM {table} WHERE deleted_at IS NULL;
+
+            CREATE TRIGGER {table}_fts_insert AFTER INSERT ON {table} BEGIN
+                INSERT INTO {table}_fts (rowid, title, body)
+                    SELECT id, {title}, {body} FROM {table} WHERE id = NEW.id AND deleted_at IS NULL;
+            END;
+            CREATE TRIGGER {table}_fts_update AFTER UPDATE ON {table} BEGIN
+                DELETE FROM {table}_fts WHERE rowid = OLD.id;
+                INSERT INTO {table}_fts (rowid, title, body)
+                    SELECT id, {title}, {body} FROM {table} WHERE id = NEW.id AND deleted_at IS NULL;
+            END;
+            CREATE TRIGGER {table}_fts_delete AFTER DELETE ON {table} BEGIN
+                DELETE FROM {table}_fts WHERE rowid = OLD.id;
+            END;
+            "
+        ))?;
+    }
+    Ok(())
+}
+
+// Household members and heads used to be stored as typed names. They are
+// matched to residents by name; names that match nobody or several people
+// are kept in `household_member_import_issues` for a clerk to resolve.
+fn v8_household_members(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE household_members (
+            household_id INTEGER NOT NULL REFERENCES households(id) ON DELETE CASCADE,
+            resident_id INTEGER NOT NULL REFERENCES residents(id) ON DELETE CASCADE,
+            relationship TEXT NOT NULL DEFAULT '',
+            PRIMARY KEY (household_id, resident_id)
+        );
+        CREATE INDEX idx_household_members_resident ON household_members(resident_id);
+
+        CREATE TABLE household_member_import_issues (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            household_id INTEGER NOT NULL REFERENCES households(id) ON DELETE CASCADE,
+            name TEXT NOT NULL,
+            is_head INTEGER NOT NULL DEFAULT 0,
+            reason TEXT NOT NULL CHECK (reason IN ('not_found', 'ambiguous'))
+        );
+
+        ALTER TABLE households ADD COLUMN head_resident_id INTEGER REFERENCES residents(id) ON DELETE SET NULL;
+        ",
+    )?;
+
+    let names = LegacyNameIndex::load(tx)?;
+    let households: Vec<(i64, String, Option<String>)> = tx
+        .prepare("SELECT id, head, selected_residents FROM households")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut add_member = tx.prepare(
+        "INSERT OR IGNORE INTO household_members (household_id, resident_id, relationship) VALUES (?1, ?2, ?3)",
+    )?;
+    let mut add_issue = tx.prepare(
+        "INSERT INTO household_member_import_issues (household_id, name, is_head, reason) VALUES (?1, ?2, ?3, ?4)",
+    )?;
+
+    for (household_id, head, selected_json) in households {
+        let selected: Vec<String> = selected_json
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        let names_with_role = std::iter::once((head, true))
+            .chain(selected.into_iter().map(|name| (name, false)));
+
+        for (name, is_head) in names_with_role {
+            if name.trim().is_empty() {
+                continue;
+            }
+            match names.find(&name) {
+                NameMatch::Found(resident_id) if is_head => {
+                    tx.execute(
+                        "UPDATE households SET head_resident_id = ?1 WHERE id = ?2",
+                        (resident_id, household_id),
+                    )?;
+                    add_member.execute((household_id, resident_id, "Head"))?;
+                }
+                NameMatch::Found(resident_id) => {
+                    add_member.execute((household_id, resident_id, ""))?;
+                }
+                NameMatch::NotFound => {
+                    add_issue.execute((household_id, &name, is_head, "not_found"))?;
+                }
+                NameMatch::Ambiguous => {
+                    add_issue.execute((household_id, &name, is_head, "ambiguous"))?;
+                }
+            }
+        }
+    }
+    drop(add_member);
+    drop(add_issue);
+
+    tx.execute_batch(
+        "
+        ALTER TABLE households DROP COLUMN selected_residents;
+        ALTER TABLE households DROP COLUMN members;
+        ALTER TABLE households DROP COLUMN head;
+        ",
+    )
+}
+
+// Existing certificates are linked by name where it identifies exactly one
+// resident; the rest keep only their typed name.
+fn v9_certificate_residents(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        ALTER TABLE certificates ADD COLUMN resident_id INTEGER REFERENCES residents(id) ON DELETE SET NULL;
+        CREATE INDEX idx_certificates_resident ON certificates(resident_id, issued_date);
+        ",
+    )?;
+
+    let names = LegacyNameIndex::load(tx)?;
+    let certificates: Vec<(i64, String)> = tx
+        .prepare("SELECT id, resident_name FROM certificates")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut link = tx.prepare("UPDATE certificates SET resident_id = ?1 WHERE id = ?2")?;
+    for (id, name) in certificates {
+        if let NameMatch::Found(resident_id) = names.find(&name) {
+            link.execute((resident_id, id))?;
+        }
+    }
+
+    Ok(())
+}
+
+// Splits the old free-text party columns into one row per name, linking
+// names that identify exactly one resident.
+fn v10_blotter_parties(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE blotter_parties (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            blotter_id INTEGER NOT NULL REFERENCES blotters(id) ON DELETE CASCADE,
+            role TEXT NOT NULL CHECK (role IN ('complainant', 'respondent', 'witness')),
+            resident_id INTEGER REFERENCES residents(id) ON DELETE SET NULL,
+            name TEXT NOT NULL,
+            details TEXT NOT NULL DEFAULT ''
+        );
+        CREATE INDEX idx_blotter_parties_blotter ON blotter_parties(blotter_id);
+        CREATE INDEX idx_blotter_parties_resident ON blotter_parties(resident_id);
+        ",
     )?;
+
+    let names = LegacyNameIndex::load(tx)?;
+    let blotters: Vec<(i64, String, String, String)> = tx
+        .prepare("SELECT id, reported_by, involved, witnesses FROM blotters")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut add_party = tx.prepare(
+        "INSERT INTO blotter_parties (blotter_id, role, resident_id, name) VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    for (blotter_id, reported_by, involved, witnesses) in blotters {
+        let columns = [
+            ("complainant", reported_by),
+            ("respondent", involved),
+            ("witness", witnesses),
+        ];
+        for (role, text) in columns {
+            for name in text.split([',', ';', '\n']).map(str::trim).filter(|n| !n.is_empty()) {
+                let resident_id = match names.find(name) {
+                    NameMatch::Found(id) => Some(id),
+                    _ => None,
+                };
+                add_party.execute((blotter_id, role, resident_id, name))?;
+            }
+        }
+    }
+
     Ok(())
 }
+
+// Replaces the free-text blotter status with a Katarungang Pambarangay stage.
+// `status` is kept as the stage's label so existing reports still read it.
+// Old cases count as filed on their incident date: closed ones as settled,
+// ones sent to the police as certified to file action, the rest as under
+// mediation.
+fn v11_blotter_case_stages(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        ALTER TABLE blotters ADD COLUMN stage TEXT NOT NULL DEFAULT 'filed'
+            CHECK (stage IN ('filed', 'mediation', 'conciliation', 'settled',
+                             'arbitration_award', 'certificate_to_file_action', 'dismissed'));
+        ALTER TABLE blotters ADD COLUMN filed_at TEXT;
+
+        UPDATE blotters SET
+            filed_at = date(incident_date),
+            stage = CASE lower(trim(status))
+                WHEN 'closed' THEN 'settled'
+                WHEN 'transferred to police' THEN 'certificate_to_file_action'
+                ELSE 'mediation'
+            END;
+        UPDATE blotters SET status = CASE stage
+            WHEN 'settled' THEN 'Settled'
+            WHEN 'certificate_to_file_action' THEN 'Certificate to File Action'
+            ELSE 'Under Mediation'
+        END;
+        CREATE INDEX idx_blotters_stage ON blotters (stage, filed_at);
+
+        CREATE TABLE blotter_case_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            blotter_id INTEGER NOT NULL REFERENCES blotters(id) ON DELETE CASCADE,
+            kind TEXT NOT NULL CHECK (kind IN ('transition', 'hearing')),
+            from_stage TEXT,
+            to_stage TEXT,
+            hearing_date TEXT,
+            notes TEXT NOT NULL DEFAULT '',
+            user_id INTEGER NOT NULL,
+            username TEXT NOT NULL,
+            occurred_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE INDEX idx_blotter_case_events_blotter ON blotter_case_events (blotter_id, id);
+        ",
+    )
+}
+
+// Moves the single hearing date of each blotter into its own hearing row.
+// Hearings already in the past are taken to have been held; their
+// attendance and minutes were never recorded.
+fn v12_blotter_hearings(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE blotter_hearings (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            blotter_id INTEGER NOT NULL REFERENCES blotters(id) ON DELETE CASCADE,
+            stage TEXT NOT NULL,
+            scheduled_at TEXT NOT NULL,
+            duration_minutes INTEGER NOT NULL CHECK (duration_minutes > 0),
+            venue TEXT NOT NULL,
+            summons_served_at TEXT,
+            status TEXT NOT NULL DEFAULT 'scheduled'
+                CHECK (status IN ('scheduled', 'held', 'rescheduled', 'cancelled')),
+            minutes TEXT NOT NULL DEFAULT '',
+            outcome TEXT NOT NULL DEFAULT '',
+            rescheduled_from INTEGER REFERENCES blotter_hearings(id) ON DELETE SET NULL
+        );
+        CREATE INDEX idx_blotter_hearings_blotter ON blotter_hearings (blotter_id, scheduled_at);
+        CREATE INDEX idx_blotter_hearings_slot ON blotter_hearings (status, scheduled_at);
+
+        CREATE TABLE blotter_hearing_attendance (
+            hearing_id INTEGER NOT NULL REFERENCES blotter_hearings(id) ON DELETE CASCADE,
+            party_id INTEGER NOT NULL REFERENCES blotter_parties(id) ON DELETE CASCADE,
+            attended INTEGER NOT NULL,
+            remarks TEXT NOT NULL DEFAULT '',
+            PRIMARY KEY (hearing_id, party_id)
+        );
+
+        INSERT INTO blotter_hearings (blotter_id, stage, scheduled_at, duration_minutes, venue, status)
+        SELECT id, stage, strftime('%Y-%m-%d %H:%M', hearing_date), 60, 'Barangay Hall',
+               CASE WHEN datetime(hearing_date) < datetime('now') THEN 'held' ELSE 'scheduled' END
+        FROM blotters
+        WHERE strftime('%Y-%m-%d %H:%M', hearing_date) IS NOT NULL;
+        ",
+    )
+}
+
+// Clearances issued despite pending cases keep the reason given and the ids
+// of the cases that were overridden.
+fn v13_clearance_override(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        ALTER TABLE certificates ADD COLUMN clearance_override TEXT;
+        ALTER TABLE certificates ADD COLUMN clearance_override_cases TEXT;
+        ",
+    )
+}
+
+// Numbers the certificates already issued in the order they were issued,
+// including deleted ones so a restored certificate keeps its number.
+fn v14_certificate_control_numbers(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE certificate_sequences (
+            type_code TEXT NOT NULL,
+            year INTEGER NOT NULL,
+            last_number INTEGER NOT NULL,
+            PRIMARY KEY (type_code, year)
+        );
+        ALTER TABLE certificates ADD COLUMN control_number TEXT;
+        CREATE UNIQUE INDEX idx_certificates_control_number ON certificates (control_number);
+
+        CREATE TABLE certificate_prints (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            certificate_id INTEGER NOT NULL REFERENCES certificates(id) ON DELETE CASCADE,
+            user_id INTEGER NOT NULL,
+            username TEXT NOT NULL,
+            printed_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE INDEX idx_certificate_prints_certificate ON certificate_prints (certificate_id);
+        ",
+    )?;
+
+    let certificates: Vec<(i64, String, Option<String>)> = tx
+        .prepare("SELECT id, type_, issued_date FROM certificates ORDER BY date(issued_date), id")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut assign = tx.prepare("UPDATE certificates SET control_number = ?1 WHERE id = ?2")?;
+    for (id, type_, issued_date) in certificates {
+        let control_number = v14_next_control_number(tx, &type_, issued_date.as_deref())?;
+        assign.execute((control_number, id))?;
+    }
+
+    Ok(())
+}
+
+// Generates this installation's signing key alongside the settings and signs
+// every certificate already issued.
+fn v15_certificate_signatures(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE certificate_signing_key (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            secret_key BLOB NOT NULL,
+            public_key BLOB NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+        ALTER TABLE certificates ADD COLUMN signed_payload TEXT;
+        ",
+    )?;
+
+    let ids: Vec<i64> = tx
+        .prepare("SELECT id FROM certificates")?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    for id in ids {
+        v15_sign_certificate(tx, id)?;
+    }
+
+    Ok(())
+}
+
+// Certificate amounts were free text; they become numbers, with anything that
+// does not read as an amount (e.g. "free") left empty. Paid certificates are
+// linked to the income recorded for them from now on; older ones are not.
+fn v16_certificate_fees(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE certificate_fees (
+            type_ TEXT PRIMARY KEY COLLATE NOCASE,
+            amount REAL NOT NULL CHECK (amount >= 0)
+        );
+
+        ALTER TABLE incomes ADD COLUMN voided_at TEXT;
+        ALTER TABLE incomes ADD COLUMN void_reason TEXT;
+
+        ALTER TABLE certificates RENAME COLUMN amount TO amount_text;
+        ALTER TABLE certificates ADD COLUMN amount REAL;
+        ALTER TABLE certificates ADD COLUMN income_id INTEGER REFERENCES incomes(id) ON DELETE SET NULL;
+        ALTER TABLE certificates ADD COLUMN voided_at TEXT;
+        ALTER TABLE certificates ADD COLUMN void_reason TEXT;
+        ",
+    )?;
+
+    let amounts: Vec<(i64, String)> = tx
+        .prepare("SELECT id, amount_text FROM certificates WHERE amount_text IS NOT NULL")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut set_amount = tx.prepare("UPDATE certificates SET amount = ?1 WHERE id = ?2")?;
+    for (id, text) in amounts {
+        let digits: String = text
+            .chars()
+            .filter(|c| c.is_ascii_digit() || *c == '.' || *c == '-')
+            .collect();
+        if let Ok(amount) = digits.parse::<f64>() {
+            set_amount.execute((amount, id))?;
+        }
+    }
+    drop(set_amount);
+
+    tx.execute("ALTER TABLE certificates DROP COLUMN amount_text", [])?;
+    Ok(())
+}
+
+// Receipt numbers become unique. Incomes that already share a number with an
+// earlier one are kept and marked as duplicates so they show up in the
+// receipt report instead of blocking the index.
+fn v17_or_booklets(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE or_booklets (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            series_start INTEGER NOT NULL CHECK (series_start > 0),
+            series_end INTEGER NOT NULL,
+            collector_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+            registered_at TEXT NOT NULL DEFAULT (datetime('now')),
+            CHECK (series_end >= series_start)
+        );
+
+        CREATE TABLE cancelled_receipts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            or_number INTEGER NOT NULL UNIQUE,
+            booklet_id INTEGER NOT NULL REFERENCES or_booklets(id),
+            reason TEXT NOT NULL,
+            user_id INTEGER NOT NULL,
+            username TEXT NOT NULL,
+            cancelled_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+
+        ALTER TABLE incomes ADD COLUMN booklet_id INTEGER REFERENCES or_booklets(id);
+        ALTER TABLE incomes ADD COLUMN duplicate_of INTEGER REFERENCES incomes(id);
+
+        UPDATE incomes
+        SET duplicate_of = (SELECT MIN(first.id) FROM incomes first WHERE first.or_number = incomes.or_number)
+        WHERE id > (SELECT MIN(first.id) FROM incomes first WHERE first.or_number = incomes.or_number);
+
+        CREATE UNIQUE INDEX idx_incomes_or_number ON incomes (or_number) WHERE duplicate_of IS NULL;
+        CREATE INDEX idx_incomes_booklet ON incomes (booklet_id, or_number);
+        ",
+    )
+}
+
+// Amounts move from floating-point pesos to whole centavos. Anything that was
+// not a whole number of centavos, or not a number at all, is rounded (or
+// zeroed) and listed in `money_rounding_report`.
+fn v18_money_centavos(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE money_rounding_report (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            table_name TEXT NOT NULL,
+            row_id INTEGER NOT NULL,
+            original TEXT NOT NULL,
+            amount INTEGER NOT NULL,
+            converted_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        ",
+    )?;
+
+    let columns = [
+        ("incomes", "INTEGER NOT NULL DEFAULT 0"),
+        ("expenses", "INTEGER NOT NULL DEFAULT 0"),
+        ("certificates", "INTEGER"),
+        ("certificate_fees", "INTEGER NOT NULL DEFAULT 0 CHECK (amount >= 0)"),
+    ];
+    for (table, definition) in columns {
+        tx.execute_batch(&format!(
+            "
+            ALTER TABLE {table} RENAME COLUMN amount TO amount_real;
+            ALTER TABLE {table} ADD COLUMN amount {definition};
+            "
+        ))?;
+
+        let amounts: Vec<(i64, Value)> = tx
+            .prepare(&format!("SELECT rowid, amount_real FROM {table} WHERE amount_real IS NOT NULL"))?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let mut set_amount = tx.prepare(&format!("UPDATE {table} SET amount = ?1 WHERE rowid = ?2"))?;
+        let mut report = tx.prepare(
+            "INSERT INTO money_rounding_report (table_name, row_id, original, amount) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for (id, value) in amounts {
+            let (amount, original, exact) = match value {
+                Value::Real(pesos) => {
+                    let amount = v18_centavos_from_pesos(pesos).unwrap_or_default();
+                    let exact = (pesos * 100.0 - amount as f64).abs() < 1e-6;
+                    (amount, pesos.to_string(), exact)
+                }
+                Value::Integer(pesos) => (pesos.saturating_mul(100), pesos.to_string(), true),
+                Value::Text(text) => match v18_parse_centavos(&text) {
+                    Some(amount) => (amount, text, true),
+                    None => (0, text, false),
+                },
+                Value::Null | Value::Blob(_) => (0, String::new(), false),
+            };
+            set_amount.execute(params![amount, id])?;
+            if !exact {
+                report.execute(params![table, id, original, amount])?;
+            }
+        }
+        drop(set_amount);
+        drop(report);
+
+        tx.execute(&format!("ALTER TABLE {table} DROP COLUMN amount_real"), [])?;
+    }
+    Ok(())
+}
+
+// One appropriation per account code and fiscal year. Existing expenses are
+// left uncharged.
+fn v19_appropriations(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE appropriations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            fiscal_year INTEGER NOT NULL,
+            account_code TEXT NOT NULL
+                CHECK (account_code IN ('ps', 'mooe', 'co', 'development_fund', 'drrm_fund', 'sk_fund')),
+            description TEXT NOT NULL DEFAULT '',
+            amount INTEGER NOT NULL CHECK (amount >= 0),
+            UNIQUE (fiscal_year, account_code)
+        );
+
+        ALTER TABLE expenses ADD COLUMN appropriation_id INTEGER REFERENCES appropriations(id);
+        ALTER TABLE expenses ADD COLUMN overspend_override TEXT;
+        CREATE INDEX idx_expenses_appropriation ON expenses (appropriation_id);
+        ",
+    )
+}
+
+// Existing incomes and expenses are put in the General Fund.
+fn v20_cashbook(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        ALTER TABLE incomes ADD COLUMN fund TEXT NOT NULL DEFAULT 'general_fund'
+            CHECK (fund IN ('general_fund', 'sk_fund', 'trust_fund'));
+        ALTER TABLE expenses ADD COLUMN fund TEXT NOT NULL DEFAULT 'general_fund'
+            CHECK (fund IN ('general_fund', 'sk_fund', 'trust_fund'));
+        CREATE INDEX idx_expenses_date ON expenses (date);
+
+        CREATE TABLE fund_opening_balances (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            fund TEXT NOT NULL UNIQUE CHECK (fund IN ('general_fund', 'sk_fund', 'trust_fund')),
+            as_of TEXT NOT NULL,
+            amount INTEGER NOT NULL
+        );
+
+        CREATE TABLE cashbook_closings (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            closed_through TEXT NOT NULL,
+            user_id INTEGER NOT NULL,
+            username TEXT NOT NULL,
+            closed_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+
+        CREATE TABLE bank_deposits (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            fund TEXT NOT NULL CHECK (fund IN ('general_fund', 'sk_fund', 'trust_fund')),
+            date TEXT NOT NULL,
+            amount INTEGER NOT NULL CHECK (amount > 0),
+            reference TEXT NOT NULL DEFAULT '',
+            cleared_date TEXT
+        );
+        CREATE INDEX idx_bank_deposits_fund ON bank_deposits (fund, date);
+        ",
+    )
+}
+
+// SQLite cannot change a CHECK constraint in place, so the table is rebuilt
+// with the same rows and ids.
+fn v21_audit_purge(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE audit_log_new (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            username TEXT NOT NULL,
+            occurred_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            table_name TEXT NOT NULL,
+            row_id INTEGER NOT NULL,
+            operation TEXT NOT NULL CHECK (operation IN ('create', 'update', 'delete', 'purge')),
+            changes TEXT NOT NULL
+        );
+        INSERT INTO audit_log_new (id, user_id, username, occurred_at, table_name, row_id, operation, changes)
+            SELECT id, user_id, username, occurred_at, table_name, row_id, operation, changes FROM audit_log;
+        DROP TABLE audit_log;
+        ALTER TABLE audit_log_new RENAME TO audit_log;
+        CREATE INDEX idx_audit_log_table_row ON audit_log (table_name, row_id);
+        CREATE INDEX idx_audit_log_occurred_at ON audit_log (occurred_at);
+        ",
+    )
+}
+
+fn v22_certificate_fee_override(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch("ALTER TABLE certificates ADD COLUMN fee_override TEXT;")
+}
+
+// SQLite cannot add an ON DELETE action to an existing column, so the table
+// is rebuilt with the same rows and ids. Certificates are unlinked from their
+// incomes while the old table is dropped and linked again afterwards; left
+// to the drop, setting income_id to NULL would fire the certificates search
+// trigger mid-drop, which SQLite refuses. duplicate_of points at the new
+// table from the start, and the rename carries that over.
+fn v23_income_duplicate_links(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TEMP TABLE certificate_incomes AS
+            SELECT id, income_id FROM certificates WHERE income_id IS NOT NULL;
+        UPDATE certificates SET income_id = NULL WHERE income_id IS NOT NULL;
+
+        CREATE TABLE incomes_new (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            type_ TEXT NOT NULL,
+            amount INTEGER NOT NULL DEFAULT 0,
+            or_number INTEGER NOT NULL,
+            received_from TEXT NOT NULL,
+            received_by TEXT NOT NULL,
+            category TEXT NOT NULL,
+            date TEXT NOT NULL,
+            fund TEXT NOT NULL DEFAULT 'general_fund'
+                CHECK (fund IN ('general_fund', 'sk_fund', 'trust_fund')),
+            booklet_id INTEGER REFERENCES or_booklets(id),
+            duplicate_of INTEGER REFERENCES incomes_new(id) ON DELETE SET NULL,
+            voided_at TEXT,
+            void_reason TEXT,
+            deleted_at TEXT,
+            deleted_by INTEGER
+        );
+        INSERT INTO incomes_new (
+            id, type_, amount, or_number, received_from, received_by, category, date, fund,
+            booklet_id, duplicate_of, voided_at, void_reason, deleted_at, deleted_by
+        )
+            SELECT id, type_, amount, or_number, received_from, received_by, category, date, fund,
+                   booklet_id, duplicate_of, voided_at, void_reason, deleted_at, deleted_by
+            FROM incomes;
+        DROP TABLE incomes;
+        ALTER TABLE incomes_new RENAME TO incomes;
+        CREATE INDEX idx_incomes_deleted_at ON incomes (deleted_at);
+        CREATE INDEX idx_incomes_date ON incomes (date);
+        CREATE UNIQUE INDEX idx_incomes_or_number ON incomes (or_number) WHERE duplicate_of IS NULL;
+        CREATE INDEX idx_incomes_booklet ON incomes (booklet_id, or_number);
+
+        UPDATE certificates
+        SET income_id = (SELECT c.income_id FROM temp.certificate_incomes c WHERE c.id = certificates.id)
+        WHERE id IN (SELECT id FROM temp.certificate_incomes);
+        DROP TABLE temp.certificate_incomes;
+        ",
+    )
+}
+
+// Zones are free text now rather than a number from 1 to 10, and are
+// filtered on by exact match, so stray spaces typed before would split a
+// zone in two.
+fn v24_trim_zones(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        UPDATE residents SET zone = trim(zone) WHERE zone <> trim(zone);
+        UPDATE households SET zone = trim(zone) WHERE zone <> trim(zone);
+        UPDATE blotters SET zone = trim(zone) WHERE zone <> trim(zone);
+        ",
+    )
+}
+
+// Audit entries point at a row by its id, which the fee schedule, keyed by
+// certificate type, did not have.
+fn v25_certificate_fee_ids(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE certificate_fees_new (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            type_ TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            amount INTEGER NOT NULL DEFAULT 0 CHECK (amount >= 0)
+        );
+        INSERT INTO certificate_fees_new (type_, amount)
+            SELECT type_, amount FROM certificate_fees ORDER BY type_;
+        DROP TABLE certificate_fees;
+        ALTER TABLE certificate_fees_new RENAME TO certificate_fees;
+        ",
+    )
+}
+
+// Certificates used to be listed with an unreadable age shown as blank; they
+// are now read strictly, so ages typed as text before are dropped once here.
+fn v26_certificate_ages(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        UPDATE certificates SET age = CAST(age AS INTEGER) WHERE typeof(age) = 'real';
+        UPDATE certificates SET age = NULL WHERE typeof(age) NOT IN ('integer', 'null');
+        ",
+    )
+}
+
+// What follows is application code as it stood when the migrations using it
+// shipped. Leave it alone even when the originals change.
+
+enum NameMatch {
+    Found(i64),
+    NotFound,
+    Ambiguous,
+}
+
+/// Residents by the free-text spellings their names were typed in before
+/// records were linked by id (v8 to v10): "First Middle Last", "First M.
+/// Last", "First Last" and "Last, First Middle", ignoring case and
+/// punctuation.
+struct LegacyNameIndex {
+    ids_by_name: HashMap<String, Vec<i64>>,
+}
+
+impl LegacyNameIndex {
+    fn load(conn: &Connection) -> rusqlite::Result<Self> {
+        let mut ids_by_name: HashMap<String, Vec<i64>> = HashMap::new();
+
+        let mut stmt = conn.prepare(
+            "SELECT id, first_name, COALESCE(middle_name, ''), last_name, COALESCE(suffix, '')
+             FROM residents WHERE deleted_at IS NULL",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })?;
+
+        for row in rows {
+            let (id, first, middle, last, suffix) = row?;
+            let initial: String = middle.chars().take(1).collect();
+            let spellings = [
+                format!("{} {} {} {}", first, middle, last, suffix),
+                format!("{} {} {}", first, middle, last),
+                format!("{} {} {} {}", first, initial, last, suffix),
+                format!("{} {} {}", first, initial, last),
+                format!("{} {} {}", first, last, suffix),
+                format!("{} {}", first, last),
+                format!("{} {} {} {}", last, first, middle, suffix),
+                format!("{} {} {}", last, first, middle),
+                format!("{} {}", last, first),
+            ];
+            for spelling in spellings {
+                let ids = ids_by_name.entry(normalize_name(&spelling)).or_default();
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+        }
+
+        Ok(LegacyNameIndex { ids_by_name })
+    }
+
+    fn find(&self, name: &str) -> NameMatch {
+        match self.ids_by_name.get(&normalize_name(name)).map(Vec::as_slice) {
+            Some([id]) => NameMatch::Found(*id),
+            Some([_, _, ..]) => NameMatch::Ambiguous,
+            _ => NameMatch::NotFound,
+        }
+    }
+}
+
+fn normalize_name(name: &str) -> String {
+    name.to_lowercase()
+        .replace([',', '.'], " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn v14_type_code(type_: &str) -> String {
+    const TYPE_CODES: &[(&str, &str)] = &[
+        ("Barangay Clearance", "CLR"),
+        ("Barangay Business Clearance", "BCL"),
+        ("Barangay Business Permit", "BPM"),
+        ("Barangay Protection Order", "BPO"),
+        ("Residency Certificate", "RES"),
+        ("Indigency Certificate", "IND"),
+        ("Unemployment Certificate", "UNE"),
+        ("Ownership Certificate", "OWN"),
+        ("Marriage Certificate", "MAR"),
+        ("Birth Certificate", "BIR"),
+        ("Solo Parent Certificate", "SPC"),
+        ("Jobseeker Certificate", "JOB"),
+        ("4Ps Certificate", "4PS"),
+    ];
+    if let Some((_, code)) = TYPE_CODES.iter().find(|(t, _)| t.eq_ignore_ascii_case(type_.trim())) {
+        return code.to_string();
+    }
+
+    let code: String = type_
+        .split_whitespace()
+        .filter(|w| !["barangay", "certificate", "of"].contains(&w.to_lowercase().as_str()))
+        .flat_map(|w| w.chars())
+        .filter(char::is_ascii_alphanumeric)
+        .take(3)
+        .collect::<String>()
+        .to_uppercase();
+    if code.is_empty() {
+        "CRT".to_string()
+    } else {
+        code
+    }
+}
+
+fn v14_next_control_number(conn: &Connection, type_: &str, issued_date: Option<&str>) -> rusqlite::Result<String> {
+    let code = v14_type_code(type_);
+    let year: i64 = conn.query_row(
+        "SELECT CAST(COALESCE(strftime('%Y', ?1), strftime('%Y', 'now')) AS INTEGER)",
+        params![issued_date],
+        |row| row.get(0),
+    )?;
+
+    let number: i64 = conn.query_row(
+        "INSERT INTO certificate_sequences (type_code, year, last_number) VALUES (?1, ?2, 1)
+         ON CONFLICT (type_code, year) DO UPDATE SET last_number = last_number + 1
+         RETURNING last_number",
+        params![code, year],
+        |row| row.get(0),
+    )?;
+
+    Ok(format!("{}-{}-{:06}", year, code, number))
+}
+
+/// The `BMS1` QR payload details.
+#[derive(Serialize)]
+struct V15Payload {
+    #[serde(rename = "n")]
+    control_number: Option<String>,
+    #[serde(rename = "r")]
+    resident_id: Option<i32>,
+    #[serde(rename = "m")]
+    resident_name: String,
+    #[serde(rename = "t")]
+    type_: String,
+    #[serde(rename = "d")]
+    issued_date: Option<String>,
+}
+
+fn v15_signing_key(conn: &Connection) -> rusqlite::Result<SigningKey> {
+    let stored: Option<Vec<u8>> = conn
+        .query_row("SELECT secret_key FROM certificate_signing_key WHERE id = 1", [], |row| row.get(0))
+        .optional()?;
+
+    match stored {
+        Some(bytes) => {
+            let bytes: [u8; 32] = bytes.try_into().map_err(|_| {
+                rusqlite::Error::FromSqlConversionFailure(0, Type::Blob, "signing key is not 32 bytes".into())
+            })?;
+            Ok(SigningKey::from_bytes(&bytes))
+        }
+        None => {
+            let mut bytes = [0u8; 32];
+            getrandom::getrandom(&mut bytes).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+            let key = SigningKey::from_bytes(&bytes);
+            conn.execute(
+                "INSERT INTO certificate_signing_key (id, secret_key, public_key) VALUES (1, ?1, ?2)",
+                params![key.to_bytes().as_slice(), key.verifying_key().to_bytes().as_slice()],
+            )?;
+            Ok(key)
+        }
+    }
+}
+
+fn v15_sign_certificate(conn: &Connection, certificate_id: i64) -> rusqlite::Result<()> {
+    let payload = conn.query_row(
+        "SELECT control_number, resident_id, resident_name, type_, issued_date FROM certificates WHERE id = ?1",
+        params![certificate_id],
+        |row| {
+            Ok(V15Payload {
+                control_number: row.get(0)?,
+                resident_id: row.get(1)?,
+                resident_name: row.get(2)?,
+                type_: row.get(3)?,
+                issued_date: row.get(4)?,
+            })
+        },
+    )?;
+
+    let details = serde_json::to_vec(&payload).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    let signature = v15_signing_key(conn)?.sign(&details);
+    let signed = format!(
+        "BMS1.{}.{}",
+        URL_SAFE_NO_PAD.encode(&details),
+        URL_SAFE_NO_PAD.encode(signature.to_bytes())
+    );
+
+    conn.execute(
+        "UPDATE certificates SET signed_payload = ?1 WHERE id = ?2",
+        params![signed, certificate_id],
+    )?;
+    Ok(())
+}
+
+/// Rounds to the nearest centavo, halves away from zero.
+fn v18_centavos_from_pesos(pesos: f64) -> Option<i64> {
+    let centavos = (pesos * 100.0).round();
+    (centavos.is_finite() && centavos.abs() < i64::MAX as f64).then_some(centavos as i64)
+}
+
+/// Reads amounts like `1234.5`, `-20` or `1,234.50`; None for anything else,
+/// including more than two decimal places.
+fn v18_parse_centavos(text: &str) -> Option<i64> {
+    let s = text.trim();
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let digits = digits.replace(',', "");
+    let (whole, fraction) = digits.split_once('.').unwrap_or((&digits, ""));
+
+    let all_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    if (whole.is_empty() && fraction.is_empty()) || !all_digits(whole) || !all_digits(fraction) || fraction.len() > 2 {
+        return None;
+    }
+
+    let whole: i64 = if whole.is_empty() { 0 } else { whole.parse().ok()? };
+    let fraction: i64 = format!("{:0<2}", fraction).parse().unwrap_or_default();
+    let centavos = whole.checked_mul(100)?.checked_add(fraction)?;
+    Some(if negative { -centavos } else { centavos })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A database as the app left it before migrations were versioned.
+    fn baseline() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "foreign_keys", "ON").unwrap();
+        let tx = conn.transaction().unwrap();
+        v1_initial_schema(&tx).unwrap();
+        tx.commit().unwrap();
+        conn.execute_batch(
+            "
+            INSERT INTO users (username, password) VALUES ('admin', 'secret');
+            INSERT INTO residents (prefix, first_name, middle_name, last_name, suffix, civil_status, gender, nationality,
+                mobile_number, date_of_birth, town_of_birth, province_of_birth, zone, barangay, town, province,
+                father_prefix, father_first_name, father_middle_name, father_last_name, father_suffix,
+                mother_prefix, mother_first_name, mother_middle_name, mother_last_name, status, photo)
+            VALUES ('', 'Juan', 'Santos', 'Dela Cruz', '', 'Single', 'Male', 'Filipino', '', '1990-01-01', '', '', ' Purok 11 ',
+                '', '', '', '', '', '', '', '', '', '', '', '', 'Active', NULL);
+            INSERT INTO incomes (type_, amount, or_number, received_from, received_by, category, date)
+            VALUES ('Clearance', 50.5, 5, 'Juan Dela Cruz', 'admin', 'Fees', '2024-01-05'),
+                   ('Clearance', 20, 5, 'Ana Reyes', 'admin', 'Fees', '2024-01-06');
+            INSERT INTO expenses (type_, amount, paid_to, paid_by, date, category, or_number)
+            VALUES ('Supplies', 99.99, 'Store', 'admin', '2024-01-07', 'Office', 1);
+            INSERT INTO certificates (resident_name, type_, age, amount, issued_date)
+            VALUES ('Juan S. Dela Cruz', 'Barangay Clearance', 'thirty', '50.00', '2024-03-01');
+            ",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn upgrades_a_baseline_database_to_the_latest_version() {
+        let mut conn = baseline();
+        let applied = migrate(&mut conn).unwrap();
+        assert_eq!(applied, MIGRATIONS.iter().map(|m| m.version).collect::<Vec<_>>());
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+
+        let dangling: i64 = conn
+            .query_row("SELECT COUNT(*) FROM pragma_foreign_key_check", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(dangling, 0);
+
+        let incomes: Vec<(i64, Option<i64>)> = conn
+            .prepare("SELECT amount, duplicate_of FROM incomes ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(incomes, vec![(5050, None), (2000, Some(1))]);
+        let expense: i64 = conn.query_row("SELECT amount FROM expenses", [], |row| row.get(0)).unwrap();
+        assert_eq!(expense, 9999);
+
+        let (resident_id, control_number, signed, amount, age): (Option<i64>, String, Option<String>, Option<i64>, Option<i32>) = conn
+            .query_row(
+                "SELECT resident_id, control_number, signed_payload, amount, age FROM certificates",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+            )
+            .unwrap();
+        assert_eq!((resident_id, control_number.as_str(), amount), (Some(1), "2024-CLR-000001", Some(5000)));
+        assert_eq!(age, None);
+        assert!(signed.is_some());
+
+        let zone: String = conn.query_row("SELECT zone FROM residents", [], |row| row.get(0)).unwrap();
+        assert_eq!(zone, "Purok 11");
+
+        // The rebuilt incomes table unlinks duplicates of a deleted income.
+        conn.execute("DELETE FROM incomes WHERE id = 1", []).unwrap();
+        let duplicate_of: Option<i64> = conn
+            .query_row("SELECT duplicate_of FROM incomes WHERE id = 2", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(duplicate_of, None);
+    }
+
+    #[test]
+    fn migrating_again_applies_nothing() {
+        let mut conn = baseline();
+        migrate(&mut conn).unwrap();
+        assert!(migrate(&mut conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn refuses_a_database_from_a_newer_version() {
+        let mut conn = baseline();
+        migrate(&mut conn).unwrap();
+        conn.execute(
+            "INSERT INTO schema_migrations (version, description) VALUES (?1, 'from the future')",
+            [latest_version() + 1],
+        )
+        .unwrap();
+        assert!(matches!(migrate(&mut conn), Err(MigrationError::DatabaseTooNew { .. })));
+    }
+}
@@ -1,2 +1,11 @@
 pub mod connection;
 pub mod migration;
+pub mod audit;
+pub mod recycle_bin;
+pub mod query;
+pub mod household;
+pub mod blotter;
+pub mod certificate;
+pub mod income;
+pub mod budget;
+pub mod cashbook;
@@ -0,0 +1,119 @@
+use crate::error::BmsError;
+use crate::models::query::{Page, PageRequest, SortDirection};
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection, Result, Row};
+
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+pub const MAX_PAGE_SIZE: i64 = 500;
+
+/// WHERE clauses joined with AND, each with at most one `?` placeholder, and
+/// the values bound to them in order.
+#[derive(Default)]
+pub struct Filters {
+    clauses: Vec<String>,
+    values: Vec<Value>,
+}
+
+impl Filters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a clause without a placeholder, e.g. `deleted_at IS NULL`.
+    pub fn raw(&mut self, clause: &str) -> &mut Self {
+        self.clauses.push(clause.to_string());
+        self
+    }
+
+    /// Adds `clause` bound to `value` if the value is present.
+    pub fn opt<T: Into<Value>>(&mut self, clause: &str, value: Option<T>) -> &mut Self {
+        if let Some(value) = value {
+            self.clauses.push(clause.to_string());
+            self.values.push(value.into());
+        }
+        self
+    }
+
+    pub fn where_sql(&self) -> String {
+        if self.clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", self.clauses.join(" AND "))
+        }
+    }
+
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+}
+
+/// What a `query_*` command pages over. `sortable` lists the columns callers
+/// may sort by; anything else falls back to `default_sort`.
+pub struct Listing {
+    pub table: &'static str,
+    pub columns: &'static str,
+    pub sortable: &'static [&'static str],
+    pub default_sort: &'static str,
+}
+
+/// Runs a paged SELECT along with a COUNT of all matching rows. Ties in the
+/// sort column are broken by id so pages never overlap. Pages are numbered
+/// from 1.
+pub fn fetch_page<T, F>(
+    conn: &Connection,
+    listing: &Listing,
+    filters: &Filters,
+    request: &PageRequest,
+    map_row: F,
+) -> std::result::Result<Page<T>, BmsError>
+where
+    F: FnMut(&Row) -> Result<T>,
+{
+    let limit = request.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let page = request.page.unwrap_or(1);
+    if page < 1 {
+        return Err(BmsError::invalid("page", "Pages are numbered from 1"));
+    }
+    let offset = (page - 1)
+        .checked_mul(limit)
+        .ok_or_else(|| BmsError::invalid("page", "Page number is too large"))?;
+
+    let where_sql = filters.where_sql();
+
+    let total: i64 = conn
+        .prepare_cached(&format!("SELECT COUNT(*) FROM {} {}", listing.table, where_sql))?
+        .query_row(params_from_iter(filters.values()), |row| row.get(0))?;
+
+    let sort_column = request
+        .sort_by
+        .as_deref()
+        .and_then(|s| listing.sortable.iter().copied().find(|c| *c == s))
+        .unwrap_or(listing.default_sort);
+    let direction = match request.sort_dir.unwrap_or_default() {
+        SortDirection::Asc => "ASC",
+        SortDirection::Desc => "DESC",
+    };
+
+    let sql = format!(
+        "SELECT {} FROM {} {} ORDER BY {} {}, id {} LIMIT {} OFFSET {}",
+        listing.columns,
+        listing.table,
+        where_sql,
+        sort_column,
+        direction,
+        direction,
+        limit,
+        offset
+    );
+    let rows = conn
+        .prepare_cached(&sql)?
+        .query_map(params_from_iter(filters.values()), map_row)?
+        .collect::<Result<Vec<T>>>()?;
+
+    Ok(Page {
+        rows,
+        total,
+        page,
+        limit,
+    })
+}
@@ -0,0 +1,144 @@
+use crate::database::audit::{record_change, snapshot, Operation};
+use crate::models::recycle_bin::DeletedRecord;
+use crate::models::user::SessionUser;
+use rusqlite::{params, Connection, ErrorCode, Result};
+
+/// Tables whose rows are soft-deleted: `delete_*` commands only stamp
+/// `deleted_at`/`deleted_by`, fetches skip stamped rows, and the row stays
+/// restorable until purged.
+pub const SOFT_DELETE_TABLES: &[&str] = &[
+    "residents",
+    "blotters",
+    "certificates",
+    "incomes",
+    "expenses",
+    "households",
+    "officials",
+    "events",
+    "logbook",
+];
+
+/// Maps caller-supplied table names onto our own constants so they can be
+/// interpolated into SQL.
+pub fn known_table(name: &str) -> Option<&'static str> {
+    SOFT_DELETE_TABLES.iter().copied().find(|t| *t == name)
+}
+
+/// Moves a row to the recycle bin. Returns `false` if it does not exist or is
+/// already deleted.
+pub fn soft_delete(conn: &Connection, user: &SessionUser, table: &'static str, id: i64) -> Result<bool> {
+    let tx = conn.unchecked_transaction()?;
+    let before = snapshot(&tx, table, id)?;
+
+    let changed = tx
+        .prepare_cached(&format!(
+            "UPDATE {} SET deleted_at = CURRENT_TIMESTAMP, deleted_by = ?2
+             WHERE id = ?1 AND deleted_at IS NULL",
+            table
+        ))?
+        .execute(params![id, user.id])?;
+
+    if changed > 0 {
+        record_change(&tx, user, table, id, Operation::Delete, before)?;
+    }
+    tx.commit()?;
+
+    Ok(changed > 0)
+}
+
+/// Takes a row back out of the recycle bin. Returns `false` if it is not
+/// currently deleted.
+pub fn restore(conn: &Connection, user: &SessionUser, table: &'static str, id: i64) -> Result<bool> {
+    let tx = conn.unchecked_transaction()?;
+    let before = snapshot(&tx, table, id)?;
+
+    let changed = tx
+        .prepare_cached(&format!(
+            "UPDATE {} SET deleted_at = NULL, deleted_by = NULL
+             WHERE id = ?1 AND deleted_at IS NOT NULL",
+            table
+        ))?
+        .execute(params![id])?;
+
+    if changed > 0 {
+        record_change(&tx, user, table, id, Operation::Update, before)?;
+    }
+    tx.commit()?;
+
+    Ok(changed > 0)
+}
+
+/// Lists deleted rows of `table`, most recently deleted first.
+pub fn deleted_rows(conn: &Connection, table: &'static str) -> Result<Vec<DeletedRecord>> {
+    let ids: Vec<(i64, String, Option<i32>)> = conn
+        .prepare_cached(&format!(
+            "SELECT id, deleted_at, deleted_by FROM {} WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC",
+            table
+        ))?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<Result<_>>()?;
+
+    let mut records = Vec::new();
+    for (row_id, deleted_at, deleted_by) in ids {
+        records.push(DeletedRecord {
+            table_name: table.to_string(),
+            row_id,
+            deleted_at,
+            deleted_by,
+            data: snapshot(conn, table, row_id)?.unwrap_or_default(),
+        });
+    }
+    Ok(records)
+}
+
+/// Permanently removes rows that have been in the recycle bin for more than
+/// `retention_days`. Each removal is audited. Rows that live records still
+/// point to, and incomes with an OR number, stay in the bin. Returns how many
+/// rows went.
+pub fn purge(conn: &Connection, user: &SessionUser, retention_days: i64) -> Result<usize> {
+    let mut tx = conn.unchecked_transaction()?;
+    let cutoff = format!("-{} days", retention_days);
+    let mut purged = 0;
+
+    for table in SOFT_DELETE_TABLES {
+        let ids: Vec<i64> = tx
+            .prepare_cached(&format!(
+                "SELECT id FROM {} WHERE deleted_at IS NOT NULL AND deleted_at <= datetime('now', ?1) {}",
+                table,
+                purgeable(table)
+            ))?
+            .query_map(params![cutoff], |row| row.get(0))?
+            .collect::<Result<_>>()?;
+
+        for id in ids {
+            // Dropping the savepoint without committing undoes a refused delete.
+            let sp = tx.savepoint()?;
+            let before = snapshot(&sp, table, id)?;
+            let deleted = sp
+                .prepare_cached(&format!("DELETE FROM {} WHERE id = ?1", table))?
+                .execute(params![id]);
+            match deleted {
+                Ok(_) => {
+                    record_change(&sp, user, table, id, Operation::Purge, before)?;
+                    sp.commit()?;
+                    purged += 1;
+                }
+                Err(rusqlite::Error::SqliteFailure(e, _)) if e.code == ErrorCode::ConstraintViolation => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    tx.commit()?;
+
+    Ok(purged)
+}
+
+/// Further condition on which rows of `table` `purge` may remove. An issued
+/// receipt has to stay accounted for in the receipt report, or its number
+/// would show up there as a gap in its booklet.
+fn purgeable(table: &str) -> &'static str {
+    match table {
+        "incomes" => "AND or_number <= 0",
+        _ => "",
+    }
+}
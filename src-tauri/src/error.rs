@@ -0,0 +1,132 @@
+use rusqlite::{ffi, ErrorCode};
+use serde::Serialize;
+use std::fmt;
+
+/// A problem with one input field, shown next to that field in the form.
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// The error every command returns. It serializes as
+/// `{"code": "not_found", "message": "...", ...}`; the frontend switches on
+/// `code`, which is stable, and shows `message` to the user.
+#[derive(Debug, Serialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum BmsError {
+    NotFound { message: String },
+    Validation { message: String, fields: Vec<FieldError> },
+    Conflict { message: String },
+    Unauthorized { message: String },
+    Database { message: String },
+    Io { message: String },
+}
+
+impl BmsError {
+    pub fn not_found(message: impl Into<String>) -> Self {
+        BmsError::NotFound { message: message.into() }
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        BmsError::Conflict { message: message.into() }
+    }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        BmsError::Unauthorized { message: message.into() }
+    }
+
+    pub fn database(message: impl Into<String>) -> Self {
+        BmsError::Database { message: message.into() }
+    }
+
+    /// A validation error for a single field.
+    pub fn invalid(field: &str, message: impl Into<String>) -> Self {
+        let message = message.into();
+        BmsError::Validation {
+            message: message.clone(),
+            fields: vec![FieldError {
+                field: field.to_string(),
+                message,
+            }],
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            BmsError::NotFound { message }
+            | BmsError::Validation { message, .. }
+            | BmsError::Conflict { message }
+            | BmsError::Unauthorized { message }
+            | BmsError::Database { message }
+            | BmsError::Io { message } => message,
+        }
+    }
+}
+
+impl fmt::Display for BmsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for BmsError {}
+
+/// Turns SQLite failures into something a clerk can act on. Constraint
+/// violations keep their meaning; anything else is logged in full and
+/// reported generically so raw SQL errors never reach the UI.
+impl From<rusqlite::Error> for BmsError {
+    fn from(e: rusqlite::Error) -> Self {
+        if let rusqlite::Error::QueryReturnedNoRows = e {
+            return BmsError::not_found("The record could not be found");
+        }
+
+        if let rusqlite::Error::SqliteFailure(err, detail) = &e {
+            match err.extended_code {
+                ffi::SQLITE_CONSTRAINT_UNIQUE | ffi::SQLITE_CONSTRAINT_PRIMARYKEY => {
+                    return BmsError::conflict("A record with the same details already exists");
+                }
+                ffi::SQLITE_CONSTRAINT_FOREIGNKEY => {
+                    return BmsError::conflict("This record is linked to other records");
+                }
+                ffi::SQLITE_CONSTRAINT_NOTNULL => {
+                    // "NOT NULL constraint failed: residents.first_name"
+                    let field = detail
+                        .as_deref()
+                        .and_then(|d| d.rsplit('.').next())
+                        .unwrap_or_default();
+                    return BmsError::invalid(field, "This field is required");
+                }
+                ffi::SQLITE_CONSTRAINT_CHECK => {
+                    return BmsError::Validation {
+                        message: "One of the values is not allowed".to_string(),
+                        fields: Vec::new(),
+                    };
+                }
+                _ => {}
+            }
+            if matches!(err.code, ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) {
+                return BmsError::database("The database is busy. Please try again");
+            }
+        }
+
+        log::error!("Database error: {}", e);
+        BmsError::database("Something went wrong while accessing the database")
+    }
+}
+
+impl From<std::io::Error> for BmsError {
+    fn from(e: std::io::Error) -> Self {
+        BmsError::Io {
+            message: e.to_string(),
+        }
+    }
+}
+
+/// Only raised for JSON we store ourselves, so a failure means a damaged row.
+impl From<serde_json::Error> for BmsError {
+    fn from(e: serde_json::Error) -> Self {
+        log::error!("Stored JSON is invalid: {}", e);
+        BmsError::database("A stored record is damaged and could not be read")
+    }
+}
@@ -1,21 +1,34 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod auth;
 mod database;
 mod commands;
+mod error;
 mod models;
-
-use commands::user::{verify_user_credentials_command, insert_user_command};
-use commands::officials::{fetch_all_officials_command,insert_official_command, save_official_command, delete_official_command};
-use commands::settings::{save_settings_command, fetch_settings_command, fetch_logo_command};
-use commands::events::{save_event_command, insert_event_command, fetch_all_events_command, delete_event_command, update_event_command};
-use commands::households::{save_household_command, insert_household_command, fetch_all_households_command, delete_household_command, update_household_command, fetch_members_by_household_command};
-use commands::expense::{save_expense_command, insert_expense_command, fetch_all_expenses_command, delete_expense_command, update_expense_command};
-use commands::income::{save_income_command, insert_income_command, fetch_all_incomes_command, delete_income_command, update_income_command};
-use commands::blotters::{save_blotter_command, insert_blotter_command, fetch_all_blotters_command, delete_blotter_command, update_blotter_command};
-use commands::residents::{insert_resident_command, fetch_all_residents_command, delete_resident_command, update_resident_command,save_resident_command};
-use commands::certificates::{insert_certificate_command, fetch_all_certificates_command, update_certificate_command, delete_certificate_command, save_certificate_command};
-use commands::logbook::{fetch_all_logbook_entries_command, insert_logbook_entry_command, update_logbook_entry_command, save_logbook_entry_command, delete_logbook_entry_command};
-use database::connection::establish_connection;
+mod validation;
+
+use commands::user::{insert_user_command, login_command, logout_command, current_user_command};
+use commands::officials::{fetch_all_officials_command,insert_official_command, save_official_command, delete_official_command, restore_official_command};
+use commands::settings::{save_settings_command, fetch_settings_command, fetch_logo_command, fetch_certificate_fees_command, save_certificate_fees_command};
+use commands::events::{save_event_command, insert_event_command, fetch_all_events_command, delete_event_command, restore_event_command, update_event_command};
+use commands::households::{save_household_command, insert_household_command, fetch_all_households_command, delete_household_command, restore_household_command, update_household_command, fetch_members_by_household_command, fetch_unmatched_household_members_command};
+use commands::expense::{save_expense_command, insert_expense_command, fetch_all_expenses_command, delete_expense_command, restore_expense_command, update_expense_command};
+use commands::budget::{fetch_appropriations_command, fetch_appropriation_command, save_appropriation_command, delete_appropriation_command};
+use commands::reports::{fetch_receipts_and_expenditures_command, fetch_monthly_cash_flow_command, fetch_category_breakdown_command, fetch_year_over_year_command};
+use commands::cashbook::{fetch_opening_balances_command, save_opening_balance_command, fetch_cashbook_command, fetch_cashbook_closing_command, close_cashbook_period_command, reopen_cashbook_period_command, record_bank_deposit_command, fetch_bank_deposits_command, clear_bank_deposit_command, delete_bank_deposit_command, fetch_bank_reconciliation_command};
+use commands::income::{save_income_command, insert_income_command, fetch_all_incomes_command, query_incomes_command, delete_income_command, restore_income_command, update_income_command, void_income_command};
+use commands::receipts::{fetch_or_booklets_command, register_or_booklet_command, assign_or_booklet_command, cancel_receipt_command, fetch_receipt_report_command};
+use commands::blotters::{save_blotter_command, insert_blotter_command, fetch_all_blotters_command, query_blotters_command, fetch_resident_cases_command, fetch_overdue_blotters_command, fetch_blotter_history_command, transition_blotter_command, delete_blotter_command, restore_blotter_command, update_blotter_command};
+use commands::hearings::{fetch_hearings_by_blotter_command, fetch_hearing_calendar_command, check_hearing_slot_command, schedule_hearing_command, reschedule_hearing_command, cancel_hearing_command, record_summons_served_command, record_hearing_result_command};
+use commands::residents::{insert_resident_command, fetch_all_residents_command, query_residents_command, fetch_resident_photo_command, delete_resident_command, restore_resident_command, update_resident_command,save_resident_command};
+use commands::certificates::{check_clearance_eligibility_command, insert_certificate_command, fetch_all_certificates_command, fetch_certificates_by_resident_command, fetch_certificate_by_control_number_command, verify_certificate_command, record_certificate_print_command, query_certificates_command, update_certificate_command, void_certificate_command, delete_certificate_command, restore_certificate_command, save_certificate_command};
+use commands::logbook::{fetch_all_logbook_entries_command, query_logbook_entries_command, insert_logbook_entry_command, update_logbook_entry_command, save_logbook_entry_command, delete_logbook_entry_command, restore_logbook_entry_command};
+use commands::audit::{fetch_audit_log_command, fetch_money_rounding_report_command};
+use commands::search::global_search_command;
+use commands::recycle_bin::{fetch_recycle_bin_command, purge_deleted_records_command};
+use auth::session::SessionState;
+use database::certificate::signing_key;
+use database::connection::{establish_connection, DbPool};
 use database::migration::migrate;
 use tauri::command;
 use tauri_plugin_shell;
@@ -34,21 +47,36 @@ fn test_db_connection() -> String {
 fn main() {
     println!("🔧 Attempting to connect and migrate DB...");
 
-    let conn = establish_connection();
-    if let Ok(conn) = conn {
-        println!("✅ Connected to DB!");
-        if let Err(e) = migrate(&conn) {
-            eprintln!("❌ Migration failed: {:?}", e);
-        } else {
-            println!("✅ Migration ran successfully!");
+    let pool = match establish_connection() {
+        Ok(mut conn) => {
+            println!("✅ Connected to DB!");
+            match migrate(&mut conn) {
+                Ok(applied) => println!("✅ Migration ran successfully! Applied: {:?}", applied),
+                Err(e) => {
+                    eprintln!("❌ Migration failed: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            // Moves a signing key left in the database out to its key file.
+            // Without the key only certificates are affected, so the app
+            // still starts and those commands report the problem.
+            if let Err(e) = signing_key(&conn) {
+                eprintln!("❌ Failed to load the certificate signing key: {}", e);
+            }
+            DbPool::new(conn)
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to connect to DB: {}", e);
+            std::process::exit(1);
         }
-    } else {
-        eprintln!("❌ Failed to connect to DB");
-    }
+    };
 
     tauri::Builder::default()
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_log::Builder::new().build())
+        .manage(pool)
+        .manage(SessionState::default())
         .invoke_handler(tauri::generate_handler![
             greet,
             test_db_connection,
@@ -56,38 +84,86 @@ fn main() {
             insert_event_command,
             fetch_all_events_command,
             delete_event_command,
+            restore_event_command,
             update_event_command,
             save_event_command,
 
             insert_household_command,
             fetch_all_households_command,
             delete_household_command,
+            restore_household_command,
             update_household_command,
             save_household_command,
             fetch_members_by_household_command,
+            fetch_unmatched_household_members_command,
             
             insert_expense_command,
             fetch_all_expenses_command,
             delete_expense_command,
+            restore_expense_command,
             update_expense_command,
             save_expense_command,
+            fetch_appropriations_command,
+            fetch_appropriation_command,
+            save_appropriation_command,
+            delete_appropriation_command,
+            fetch_receipts_and_expenditures_command,
+            fetch_monthly_cash_flow_command,
+            fetch_category_breakdown_command,
+            fetch_year_over_year_command,
+            fetch_opening_balances_command,
+            save_opening_balance_command,
+            fetch_cashbook_command,
+            fetch_cashbook_closing_command,
+            close_cashbook_period_command,
+            reopen_cashbook_period_command,
+            record_bank_deposit_command,
+            fetch_bank_deposits_command,
+            clear_bank_deposit_command,
+            delete_bank_deposit_command,
+            fetch_bank_reconciliation_command,
 
             insert_income_command,
             fetch_all_incomes_command,
+            query_incomes_command,
             delete_income_command,
+            restore_income_command,
             update_income_command,
             save_income_command,
+            void_income_command,
+            fetch_or_booklets_command,
+            register_or_booklet_command,
+            assign_or_booklet_command,
+            cancel_receipt_command,
+            fetch_receipt_report_command,
 
             insert_blotter_command,
             fetch_all_blotters_command,
+            query_blotters_command,
+            fetch_resident_cases_command,
             delete_blotter_command,
+            restore_blotter_command,
             update_blotter_command,
             save_blotter_command,
+            fetch_overdue_blotters_command,
+            fetch_blotter_history_command,
+            transition_blotter_command,
+            fetch_hearings_by_blotter_command,
+            fetch_hearing_calendar_command,
+            check_hearing_slot_command,
+            schedule_hearing_command,
+            reschedule_hearing_command,
+            cancel_hearing_command,
+            record_summons_served_command,
+            record_hearing_result_command,
 
             // Residents
             insert_resident_command,
             fetch_all_residents_command,
+            query_residents_command,
+            fetch_resident_photo_command,
             delete_resident_command,
+            restore_resident_command,
             update_resident_command,
             save_resident_command,
 
@@ -95,25 +171,49 @@ fn main() {
             insert_official_command,
             save_official_command,
             delete_official_command,
+            restore_official_command,
 
             fetch_settings_command,
             save_settings_command,
+            fetch_certificate_fees_command,
+            save_certificate_fees_command,
             fetch_logo_command,
 
+            check_clearance_eligibility_command,
             insert_certificate_command,
             fetch_all_certificates_command,
+            fetch_certificates_by_resident_command,
+            fetch_certificate_by_control_number_command,
+            verify_certificate_command,
+            record_certificate_print_command,
+            query_certificates_command,
             update_certificate_command,
+            void_certificate_command,
             delete_certificate_command,
+            restore_certificate_command,
             save_certificate_command,
 
             fetch_all_logbook_entries_command,
+
+            query_logbook_entries_command,
             insert_logbook_entry_command,
             update_logbook_entry_command,
             save_logbook_entry_command,
             delete_logbook_entry_command,
+            restore_logbook_entry_command,
 
-            verify_user_credentials_command,
             insert_user_command,
+            login_command,
+            logout_command,
+            current_user_command,
+
+            fetch_audit_log_command,
+            fetch_money_rounding_report_command,
+
+            fetch_recycle_bin_command,
+            purge_deleted_records_command,
+
+            global_search_command,
             
         ])
         .run(tauri::generate_context!())
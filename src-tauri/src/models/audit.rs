@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub user_id: i32,
+    pub username: String,
+    pub occurred_at: String,
+    pub table_name: String,
+    pub row_id: i64,
+    pub operation: String,
+    pub changes: serde_json::Value,
+}
+
+/// Every field is optional; unset fields do not filter. Dates are inclusive
+/// and compared against `occurred_at` (UTC, "YYYY-MM-DD HH:MM:SS").
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditLogFilter {
+    pub table_name: Option<String>,
+    pub row_id: Option<i64>,
+    pub user_id: Option<i32>,
+    pub operation: Option<String>,
+    pub date_from: Option<String>,
+    pub date_to: Option<String>,
+    pub limit: Option<i64>,
+}
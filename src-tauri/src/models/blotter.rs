@@ -1,19 +1,273 @@
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use crate::error::BmsError;
+use crate::validation::{Validate, Validator};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Blotter {
     pub id: Option<i32>,
     pub type_: String,
+    /// `reported_by`, `involved` and `witnesses` list the names of the
+    /// parties in each role. They are rebuilt from `parties` on save.
+    #[serde(default)]
     pub reported_by: String,
+    #[serde(default)]
     pub involved: String,
     pub incident_date: String,
     pub location: String,
     pub zone: String,
+    /// Display label of `stage`; ignored on save.
+    #[serde(default)]
     pub status: String,
     pub narrative: String,
     pub action: String,
+    #[serde(default)]
     pub witnesses: String,
     pub evidence: String,
     pub resolution: String,
+    /// Next scheduled hearing, or the last one held. Kept in step with
+    /// `blotter_hearings`; ignored on save.
+    #[serde(default)]
     pub hearing_date: String,
+    #[serde(default)]
+    pub parties: Vec<BlotterParty>,
+    /// Changed only through `transition_blotter_command`; ignored on save.
+    #[serde(default)]
+    pub stage: CaseStage,
+    /// Date the complaint was filed with the barangay; today if left out
+    /// when the case is created.
+    pub filed_at: Option<String>,
+    /// Last day for the Punong Barangay to mediate; ignored on save.
+    #[serde(default)]
+    pub mediation_deadline: Option<String>,
+    /// Last day for the Pangkat to conciliate; ignored on save.
+    #[serde(default)]
+    pub conciliation_deadline: Option<String>,
+}
+
+/// Where a case stands in the Katarungang Pambarangay process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaseStage {
+    #[default]
+    Filed,
+    /// Before the Punong Barangay.
+    Mediation,
+    /// Before the Pangkat ng Tagapagkasundo of the Lupon.
+    Conciliation,
+    Settled,
+    ArbitrationAward,
+    CertificateToFileAction,
+    Dismissed,
+}
+
+impl CaseStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CaseStage::Filed => "filed",
+            CaseStage::Mediation => "mediation",
+            CaseStage::Conciliation => "conciliation",
+            CaseStage::Settled => "settled",
+            CaseStage::ArbitrationAward => "arbitration_award",
+            CaseStage::CertificateToFileAction => "certificate_to_file_action",
+            CaseStage::Dismissed => "dismissed",
+        }
+    }
+
+    /// Shown as the blotter's `status`.
+    pub fn label(&self) -> &'static str {
+        match self {
+            CaseStage::Filed => "Filed",
+            CaseStage::Mediation => "Under Mediation",
+            CaseStage::Conciliation => "Under Conciliation",
+            CaseStage::Settled => "Settled",
+            CaseStage::ArbitrationAward => "Arbitration Award",
+            CaseStage::CertificateToFileAction => "Certificate to File Action",
+            CaseStage::Dismissed => "Dismissed",
+        }
+    }
+
+    /// Whether the barangay is done with the case.
+    pub fn is_closed(&self) -> bool {
+        matches!(
+            self,
+            CaseStage::Settled
+                | CaseStage::ArbitrationAward
+                | CaseStage::CertificateToFileAction
+                | CaseStage::Dismissed
+        )
+    }
+
+    /// The moves the Lupon may make from this stage. A case goes to the
+    /// Pangkat only when mediation fails, and a certificate to file action is
+    /// issued only once conciliation has failed too.
+    pub fn can_move_to(&self, next: CaseStage) -> bool {
+        use CaseStage::*;
+        matches!(
+            (self, next),
+            (Filed, Mediation)
+                | (Filed, Dismissed)
+                | (Mediation, Settled)
+                | (Mediation, ArbitrationAward)
+                | (Mediation, Conciliation)
+                | (Mediation, Dismissed)
+                | (Conciliation, Settled)
+                | (Conciliation, ArbitrationAward)
+                | (Conciliation, CertificateToFileAction)
+                | (Conciliation, Dismissed)
+        )
+    }
+}
+
+impl FromStr for CaseStage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "filed" => Ok(CaseStage::Filed),
+            "mediation" => Ok(CaseStage::Mediation),
+            "conciliation" => Ok(CaseStage::Conciliation),
+            "settled" => Ok(CaseStage::Settled),
+            "arbitration_award" => Ok(CaseStage::ArbitrationAward),
+            "certificate_to_file_action" => Ok(CaseStage::CertificateToFileAction),
+            "dismissed" => Ok(CaseStage::Dismissed),
+            other => Err(format!("Unknown case stage: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaseEventKind {
+    Transition,
+    Hearing,
+}
+
+impl CaseEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CaseEventKind::Transition => "transition",
+            CaseEventKind::Hearing => "hearing",
+        }
+    }
+}
+
+impl FromStr for CaseEventKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "transition" => Ok(CaseEventKind::Transition),
+            "hearing" => Ok(CaseEventKind::Hearing),
+            other => Err(format!("Unknown case event: {}", other)),
+        }
+    }
+}
+
+/// One line of a case's history: a move between stages or a hearing.
+#[derive(Debug, Serialize)]
+pub struct CaseEvent {
+    pub id: i32,
+    pub blotter_id: i32,
+    pub kind: CaseEventKind,
+    pub from_stage: Option<CaseStage>,
+    pub to_stage: Option<CaseStage>,
+    pub hearing_date: Option<String>,
+    pub notes: String,
+    pub occurred_at: String,
+    pub username: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PartyRole {
+    Complainant,
+    Respondent,
+    Witness,
+}
+
+impl PartyRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PartyRole::Complainant => "complainant",
+            PartyRole::Respondent => "respondent",
+            PartyRole::Witness => "witness",
+        }
+    }
+}
+
+impl FromStr for PartyRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "complainant" => Ok(PartyRole::Complainant),
+            "respondent" => Ok(PartyRole::Respondent),
+            "witness" => Ok(PartyRole::Witness),
+            other => Err(format!("Unknown party role: {}", other)),
+        }
+    }
+}
+
+/// Someone involved in a blotter case. Residents are linked by
+/// `resident_id` and their name is filled in from the resident record;
+/// outsiders are recorded by `name` and free-form `details` such as an
+/// address or contact number.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlotterParty {
+    pub id: Option<i32>,
+    pub role: PartyRole,
+    pub resident_id: Option<i32>,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub details: String,
+}
+
+/// A blotter case as it concerns one resident.
+#[derive(Debug, Serialize)]
+pub struct ResidentCase {
+    pub blotter_id: i32,
+    pub role: PartyRole,
+    pub type_: String,
+    pub incident_date: String,
+    pub stage: CaseStage,
+    pub status: String,
+    pub reported_by: String,
+    pub involved: String,
+    /// Whether the case is still open.
+    pub pending: bool,
+}
+
+/// Filters for `query_blotters_command`; unset fields do not filter.
+/// Incident dates are inclusive ISO dates.
+#[derive(Debug, Default, Deserialize)]
+pub struct BlotterFilter {
+    pub zone: Option<String>,
+    pub stage: Option<CaseStage>,
+    pub type_: Option<String>,
+    pub resident_id: Option<i32>,
+    pub date_from: Option<String>,
+    pub date_to: Option<String>,
+}
+
+impl Validate for Blotter {
+    fn validate(&self) -> Result<(), BmsError> {
+        let has_role = |role| self.parties.iter().any(|p| p.role == role);
+        let parties_named = self
+            .parties
+            .iter()
+            .all(|p| p.resident_id.is_some() || !p.name.trim().is_empty());
+
+        Validator::new()
+            .required("type_", &self.type_)
+            .check(has_role(PartyRole::Complainant), "parties", "Add at least one complainant")
+            .check(has_role(PartyRole::Respondent), "parties", "Add at least one respondent")
+            .check(parties_named, "parties", "Every party needs a resident or a name")
+            .date("incident_date", &self.incident_date)
+            .zone("zone", &self.zone)
+            .optional_date("filed_at", self.filed_at.as_deref().unwrap_or_default())
+            .required("narrative", &self.narrative)
+            .finish()
+    }
 }
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use crate::error::BmsError;
+use crate::models::cashbook::Fund;
+use crate::models::money::Money;
+use crate::validation::{Validate, Validator};
+
+/// Account classes of the barangay's annual budget, declared in the order
+/// they appear in budget documents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountCode {
+    /// Personal Services: honoraria, salaries and benefits.
+    Ps,
+    /// Maintenance and Other Operating Expenses.
+    Mooe,
+    /// Capital Outlay.
+    Co,
+    /// 20% of the IRA/NTA set aside for development projects.
+    DevelopmentFund,
+    /// 5% Disaster Risk Reduction and Management Fund.
+    DrrmFund,
+    /// 10% of the general fund given to the Sangguniang Kabataan.
+    SkFund,
+}
+
+impl AccountCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountCode::Ps => "ps",
+            AccountCode::Mooe => "mooe",
+            AccountCode::Co => "co",
+            AccountCode::DevelopmentFund => "development_fund",
+            AccountCode::DrrmFund => "drrm_fund",
+            AccountCode::SkFund => "sk_fund",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AccountCode::Ps => "Personal Services",
+            AccountCode::Mooe => "Maintenance and Other Operating Expenses",
+            AccountCode::Co => "Capital Outlay",
+            AccountCode::DevelopmentFund => "20% Development Fund",
+            AccountCode::DrrmFund => "5% DRRM Fund",
+            AccountCode::SkFund => "SK 10%",
+        }
+    }
+
+    /// The fund expenses charged to this account are paid from. Everything
+    /// but the SK share is appropriated out of the general fund.
+    pub fn fund(&self) -> Fund {
+        match self {
+            AccountCode::SkFund => Fund::Sk,
+            _ => Fund::General,
+        }
+    }
+}
+
+impl FromStr for AccountCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ps" => Ok(AccountCode::Ps),
+            "mooe" => Ok(AccountCode::Mooe),
+            "co" => Ok(AccountCode::Co),
+            "development_fund" => Ok(AccountCode::DevelopmentFund),
+            "drrm_fund" => Ok(AccountCode::DrrmFund),
+            "sk_fund" => Ok(AccountCode::SkFund),
+            other => Err(format!("Unknown account code: {}", other)),
+        }
+    }
+}
+
+/// The amount the barangay council approved for one account in a fiscal
+/// year. Expenses charged to it are its obligations.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Appropriation {
+    pub id: Option<i32>,
+    pub fiscal_year: i32,
+    pub account_code: AccountCode,
+    #[serde(default)]
+    pub description: String,
+    pub amount: Money,
+    /// Expenses charged so far, and what is left of the amount; ignored on
+    /// save.
+    #[serde(default)]
+    pub obligated: Money,
+    #[serde(default)]
+    pub remaining: Money,
+}
+
+impl Validate for Appropriation {
+    fn validate(&self) -> Result<(), BmsError> {
+        Validator::new()
+            .check((2000..=2100).contains(&self.fiscal_year), "fiscal_year", "Enter a valid fiscal year")
+            .non_negative("amount", self.amount)
+            .finish()
+    }
+}
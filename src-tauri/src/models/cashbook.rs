@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use crate::error::BmsError;
+use crate::models::money::Money;
+use crate::models::report::ReportPeriod;
+use crate::validation::{Validate, Validator};
+
+/// The barangay's separate funds. Every income and expense belongs to one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Fund {
+    #[default]
+    #[serde(rename = "general_fund")]
+    General,
+    #[serde(rename = "sk_fund")]
+    Sk,
+    #[serde(rename = "trust_fund")]
+    Trust,
+}
+
+impl Fund {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Fund::General => "general_fund",
+            Fund::Sk => "sk_fund",
+            Fund::Trust => "trust_fund",
+        }
+    }
+}
+
+impl FromStr for Fund {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "general_fund" => Ok(Fund::General),
+            "sk_fund" => Ok(Fund::Sk),
+            "trust_fund" => Ok(Fund::Trust),
+            other => Err(format!("Unknown fund: {}", other)),
+        }
+    }
+}
+
+/// What a fund held at the start of `as_of`. The cashbook starts there;
+/// entries dated earlier are left out of it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpeningBalance {
+    pub fund: Fund,
+    pub as_of: String,
+    pub amount: Money,
+}
+
+impl Validate for OpeningBalance {
+    fn validate(&self) -> Result<(), BmsError> {
+        Validator::new()
+            .date("as_of", &self.as_of)
+            .non_negative("amount", self.amount)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CashbookEntryKind {
+    Receipt,
+    Disbursement,
+}
+
+/// An income or expense in the cashbook, with the fund's balance after it.
+#[derive(Debug, Serialize)]
+pub struct CashbookEntry {
+    pub kind: CashbookEntryKind,
+    /// Id of the income or expense.
+    pub id: i32,
+    pub date: String,
+    pub or_number: i32,
+    pub particulars: String,
+    pub receipt: Money,
+    pub disbursement: Money,
+    pub balance: Money,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Cashbook {
+    pub fund: Fund,
+    pub period: ReportPeriod,
+    /// The balance at the start of the period.
+    pub balance_forward: Money,
+    pub entries: Vec<CashbookEntry>,
+    pub total_receipts: Money,
+    pub total_disbursements: Money,
+    pub closing_balance: Money,
+    /// Entries up to this date are locked.
+    pub closed_through: Option<String>,
+}
+
+/// The books are closed through `closed_through`: incomes, expenses and
+/// deposits dated up to it can no longer change.
+#[derive(Debug, Serialize)]
+pub struct CashbookClosing {
+    pub closed_through: String,
+    pub username: String,
+    pub closed_at: String,
+}
+
+/// Collections of a fund deposited in the bank.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BankDeposit {
+    pub id: Option<i32>,
+    pub fund: Fund,
+    pub date: String,
+    pub amount: Money,
+    /// Deposit slip number.
+    #[serde(default)]
+    pub reference: String,
+    /// When the deposit showed up on the bank statement; set with
+    /// `clear_bank_deposit_command` and ignored on save.
+    #[serde(default)]
+    pub cleared_date: Option<String>,
+}
+
+impl Validate for BankDeposit {
+    fn validate(&self) -> Result<(), BmsError> {
+        Validator::new()
+            .date("date", &self.date)
+            .check(self.amount > Money::ZERO, "amount", "Enter the amount deposited")
+            .finish()
+    }
+}
+
+/// A fund's books against its bank statement as of a date. The opening
+/// balance is taken to be in the bank, and disbursements to have cleared.
+#[derive(Debug, Serialize)]
+pub struct BankReconciliation {
+    pub fund: Fund,
+    pub as_of: String,
+    /// Cashbook balance at the end of `as_of`.
+    pub book_balance: Money,
+    pub statement_balance: Money,
+    /// Collected but not yet deposited.
+    pub undeposited_collections: Money,
+    /// Deposited but not on the statement yet.
+    pub deposits_in_transit: Vec<BankDeposit>,
+    /// The statement balance plus deposits in transit and undeposited
+    /// collections.
+    pub adjusted_bank_balance: Money,
+    /// Book balance less adjusted bank balance; zero when reconciled.
+    pub difference: Money,
+    pub reconciled: bool,
+}
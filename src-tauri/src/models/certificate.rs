@@ -1,13 +1,188 @@
 use serde::{Deserialize, Serialize};
+use crate::error::BmsError;
+use crate::models::blotter::ResidentCase;
+use crate::models::money::Money;
+use crate::validation::{Validate, Validator};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Certificate {
     pub id: i32,
+    pub resident_id: Option<i32>,
+    /// Name, age and civil status are copied from the resident when the
+    /// certificate is issued and are ignored on save.
+    #[serde(default)]
     pub resident_name: String,
     pub type_: String,
     pub age: Option<i32>,
     pub civil_status: Option<String>,
     pub ownership_text: Option<String>,
-    pub amount: Option<String>,
+    /// Fee paid. Defaults to the scheduled fee for the type when issuing; a
+    /// different amount, such as 0 to waive it, needs a `fee_override`.
+    /// Fixed once issued.
+    pub amount: Option<Money>,
+    /// Why the fee differs from the schedule. Required in that case, and kept
+    /// with the certificate; ignored on save.
+    #[serde(default)]
+    pub fee_override: Option<String>,
     pub issued_date: Option<String>,
-}
\ No newline at end of file
+    /// Why a clearance was issued to a resident with pending cases as
+    /// respondent. Required in that case, and kept with the certificate.
+    #[serde(default)]
+    pub clearance_override: Option<String>,
+    /// Assigned at issuance, e.g. `2026-CLR-000123`; ignored on save.
+    #[serde(default)]
+    pub control_number: Option<String>,
+    /// How many times the certificate has been printed; ignored on save.
+    #[serde(default)]
+    pub print_count: i32,
+    /// Signed details to print as a QR code; ignored on save.
+    #[serde(default)]
+    pub qr_payload: Option<String>,
+    /// The income recorded for the fee, and its receipt number; ignored on save.
+    #[serde(default)]
+    pub income_id: Option<i32>,
+    #[serde(default)]
+    pub or_number: Option<i32>,
+    /// Set by `void_certificate_command`; ignored on save.
+    #[serde(default)]
+    pub voided_at: Option<String>,
+    #[serde(default)]
+    pub void_reason: Option<String>,
+}
+
+/// What a certificate's QR code vouches for. Keys are kept short so the
+/// code stays small enough to scan from paper.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct CertificatePayload {
+    #[serde(rename = "n")]
+    pub control_number: Option<String>,
+    #[serde(rename = "r")]
+    pub resident_id: Option<i32>,
+    #[serde(rename = "m")]
+    pub resident_name: String,
+    #[serde(rename = "t")]
+    pub type_: String,
+    #[serde(rename = "d")]
+    pub issued_date: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationStatus {
+    /// Signed here and matches a certificate on record.
+    Valid,
+    /// Not a certificate QR code, or damaged.
+    Malformed,
+    /// Not signed by this barangay, or altered.
+    BadSignature,
+    /// Signed here, but no certificate with that control number exists.
+    NotFound,
+    /// Signed here, but the certificate has since been voided or deleted.
+    Revoked,
+    /// Signed here, but the certificate on record has different details,
+    /// e.g. it was corrected after this copy was printed.
+    Mismatch,
+}
+
+/// Result of `verify_certificate_command`.
+#[derive(Debug, Serialize)]
+pub struct CertificateVerification {
+    pub status: VerificationStatus,
+    pub valid: bool,
+    /// The details the QR code claims, if its signature checks out.
+    pub payload: Option<CertificatePayload>,
+    /// The certificate on record, if any.
+    pub certificate: Option<Certificate>,
+}
+
+/// Returned when a certificate is printed, so the copy can be marked as a
+/// reprint.
+#[derive(Debug, Serialize)]
+pub struct CertificatePrint {
+    pub certificate_id: i32,
+    pub control_number: Option<String>,
+    /// Includes this print.
+    pub print_count: i32,
+    pub is_reprint: bool,
+    /// When the certificate was first printed.
+    pub first_printed_at: String,
+}
+
+/// Whether a resident can be issued a barangay clearance.
+#[derive(Debug, Serialize)]
+pub struct ClearanceEligibility {
+    pub resident_id: i32,
+    pub eligible: bool,
+    /// Open cases in which the resident is a respondent.
+    pub blocking_cases: Vec<ResidentCase>,
+}
+
+/// Codes used in control numbers for the certificates the app prints.
+const TYPE_CODES: &[(&str, &str)] = &[
+    ("Barangay Clearance", "CLR"),
+    ("Barangay Business Clearance", "BCL"),
+    ("Barangay Business Permit", "BPM"),
+    ("Barangay Protection Order", "BPO"),
+    ("Residency Certificate", "RES"),
+    ("Indigency Certificate", "IND"),
+    ("Unemployment Certificate", "UNE"),
+    ("Ownership Certificate", "OWN"),
+    ("Marriage Certificate", "MAR"),
+    ("Birth Certificate", "BIR"),
+    ("Solo Parent Certificate", "SPC"),
+    ("Jobseeker Certificate", "JOB"),
+    ("4Ps Certificate", "4PS"),
+];
+
+/// The code of a certificate type in its control number. Types without a
+/// code of their own use their first three letters, ignoring the words
+/// "Barangay", "Certificate" and "of".
+pub fn type_code(type_: &str) -> String {
+    if let Some((_, code)) = TYPE_CODES.iter().find(|(t, _)| t.eq_ignore_ascii_case(type_.trim())) {
+        return code.to_string();
+    }
+
+    let code: String = type_
+        .split_whitespace()
+        .filter(|w| !["barangay", "certificate", "of"].contains(&w.to_lowercase().as_str()))
+        .flat_map(|w| w.chars())
+        .filter(char::is_ascii_alphanumeric)
+        .take(3)
+        .collect::<String>()
+        .to_uppercase();
+    if code.is_empty() {
+        "CRT".to_string()
+    } else {
+        code
+    }
+}
+
+/// Clearances of any kind (barangay, business, ...) are withheld from
+/// residents with pending cases.
+pub fn is_clearance(type_: &str) -> bool {
+    type_.to_lowercase().contains("clearance")
+}
+
+/// Filters for `query_certificates_command`; unset fields do not filter.
+/// Issue dates are inclusive ISO dates.
+#[derive(Debug, Default, Deserialize)]
+pub struct CertificateFilter {
+    pub type_: Option<String>,
+    pub resident_id: Option<i32>,
+    pub resident_name: Option<String>,
+    pub control_number: Option<String>,
+    pub date_from: Option<String>,
+    pub date_to: Option<String>,
+}
+
+impl Validate for Certificate {
+    fn validate(&self) -> Result<(), BmsError> {
+        Validator::new()
+            .required("type_", &self.type_)
+            // Certificates issued before residents were linked may lack one.
+            .check(self.id != 0 || self.resident_id.is_some(), "resident_id", "Choose the resident this certificate is for")
+            .non_negative("amount", self.amount.unwrap_or_default())
+            .optional_date("issued_date", self.issued_date.as_deref().unwrap_or_default())
+            .finish()
+    }
+}
@@ -1,4 +1,6 @@
 use serde::{Serialize, Deserialize};
+use crate::error::BmsError;
+use crate::validation::{Validate, Validator, EVENT_STATUSES};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Event {
@@ -10,4 +12,16 @@ pub struct Event {
     pub venue: String,
     pub attendee: String,
     pub notes: String,
-}
\ No newline at end of file
+}
+
+impl Validate for Event {
+    fn validate(&self) -> Result<(), BmsError> {
+        Validator::new()
+            .required("name", &self.name)
+            .required("type_", &self.type_)
+            .one_of("status", &self.status, EVENT_STATUSES)
+            .date("date", &self.date)
+            .required("venue", &self.venue)
+            .finish()
+    }
+}
@@ -1,13 +1,39 @@
 use serde::{Serialize, Deserialize};
+use crate::error::BmsError;
+use crate::models::cashbook::Fund;
+use crate::models::money::Money;
+use crate::validation::{Validate, Validator};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Expense {
     pub id: Option<i32>,
     pub type_: String,
-    pub amount: f64,
+    pub amount: Money,
     pub or_number: i32,
     pub paid_to: String,
     pub paid_by: String,
     pub date: String,
     pub category: String,
-}
\ No newline at end of file
+    #[serde(default)]
+    pub fund: Fund,
+    /// The budget appropriation the expense is charged to.
+    #[serde(default)]
+    pub appropriation_id: Option<i32>,
+    /// Why the expense was allowed to exceed what was left of its
+    /// appropriation. Required in that case.
+    #[serde(default)]
+    pub overspend_override: Option<String>,
+}
+
+impl Validate for Expense {
+    fn validate(&self) -> Result<(), BmsError> {
+        Validator::new()
+            .required("type_", &self.type_)
+            .non_negative("amount", self.amount)
+            .check(self.or_number > 0, "or_number", "OR number must be a positive number")
+            .required("paid_to", &self.paid_to)
+            .required("category", &self.category)
+            .date("date", &self.date)
+            .finish()
+    }
+}
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use crate::error::BmsError;
+use crate::models::blotter::{CaseStage, PartyRole};
+use crate::validation::{Validate, Validator};
+
+/// Longest a single hearing may be booked for.
+pub const MAX_HEARING_MINUTES: i32 = 8 * 60;
+
+/// One summons and hearing session of a blotter case.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Hearing {
+    pub id: Option<i32>,
+    pub blotter_id: i32,
+    /// Stage the case was in when the hearing was scheduled; ignored on save.
+    #[serde(default)]
+    pub stage: CaseStage,
+    /// `YYYY-MM-DD HH:MM`.
+    pub scheduled_at: String,
+    pub duration_minutes: i32,
+    pub venue: String,
+    /// Date the summons was served on the respondents, if it has been.
+    pub summons_served_at: Option<String>,
+    /// Changed only through the hearing commands; ignored on save.
+    #[serde(default)]
+    pub status: HearingStatus,
+    #[serde(default)]
+    pub minutes: String,
+    #[serde(default)]
+    pub outcome: String,
+    /// The hearing this one replaced when it was rescheduled.
+    #[serde(default)]
+    pub rescheduled_from: Option<i32>,
+    /// Every party to the case, with their attendance if it was recorded.
+    #[serde(default)]
+    pub attendance: Vec<HearingAttendance>,
+    /// Case number and type, for the calendar; ignored on save.
+    #[serde(default)]
+    pub case_title: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HearingStatus {
+    #[default]
+    Scheduled,
+    Held,
+    Rescheduled,
+    Cancelled,
+}
+
+impl HearingStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HearingStatus::Scheduled => "scheduled",
+            HearingStatus::Held => "held",
+            HearingStatus::Rescheduled => "rescheduled",
+            HearingStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl FromStr for HearingStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "scheduled" => Ok(HearingStatus::Scheduled),
+            "held" => Ok(HearingStatus::Held),
+            "rescheduled" => Ok(HearingStatus::Rescheduled),
+            "cancelled" => Ok(HearingStatus::Cancelled),
+            other => Err(format!("Unknown hearing status: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HearingAttendance {
+    pub party_id: i32,
+    #[serde(default)]
+    pub role: Option<PartyRole>,
+    #[serde(default)]
+    pub name: String,
+    /// `None` until attendance is taken; `Some(false)` is a no-show.
+    pub attended: Option<bool>,
+    #[serde(default)]
+    pub remarks: String,
+}
+
+/// A new slot for a hearing that cannot go ahead as booked. Duration and
+/// venue stay the same unless given.
+#[derive(Debug, Deserialize)]
+pub struct HearingReschedule {
+    pub scheduled_at: String,
+    pub duration_minutes: Option<i32>,
+    pub venue: Option<String>,
+    pub reason: String,
+}
+
+/// What happened at a hearing, recorded once it has been held.
+#[derive(Debug, Deserialize)]
+pub struct HearingResult {
+    pub attendance: Vec<HearingAttendance>,
+    pub minutes: String,
+    pub outcome: String,
+}
+
+/// The checks on a hearing's time slot, shared with the slot lookup the form
+/// makes before saving.
+pub fn check_slot<'a>(validator: &'a mut Validator, scheduled_at: &str, duration_minutes: i32) -> &'a mut Validator {
+    validator.datetime("scheduled_at", scheduled_at).check(
+        (1..=MAX_HEARING_MINUTES).contains(&duration_minutes),
+        "duration_minutes",
+        "A hearing lasts between 1 minute and 8 hours",
+    )
+}
+
+impl Validate for Hearing {
+    fn validate(&self) -> Result<(), BmsError> {
+        check_slot(&mut Validator::new(), &self.scheduled_at, self.duration_minutes)
+            .required("venue", &self.venue)
+            .optional_date("summons_served_at", self.summons_served_at.as_deref().unwrap_or_default())
+            .finish()
+    }
+}
+
+impl Validate for HearingResult {
+    fn validate(&self) -> Result<(), BmsError> {
+        Validator::new()
+            .check(
+                self.attendance.iter().all(|a| a.attended.is_some()),
+                "attendance",
+                "Mark every party present or absent",
+            )
+            .required("minutes", &self.minutes)
+            .finish()
+    }
+}
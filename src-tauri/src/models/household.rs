@@ -1,14 +1,64 @@
 use serde::{Deserialize, Serialize};
+use crate::error::BmsError;
+use crate::validation::{Validate, Validator, HOUSEHOLD_STATUSES};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Household {
     pub id: Option<i32>,
     pub household_number: i32,
     pub type_: String,
+    /// Number of members, counted from `household_members`; ignored on save.
+    #[serde(default)]
     pub members: i32,
+    pub head_resident_id: Option<i32>,
+    /// Name of the head resident; ignored on save.
+    #[serde(default)]
     pub head: String,
     pub zone: String,
     pub date: String,
     pub status: String,
-    pub selected_residents: Vec<String>,
-}
\ No newline at end of file
+    #[serde(default)]
+    pub residents: Vec<HouseholdMember>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HouseholdMember {
+    pub resident_id: i32,
+    /// The resident's name; ignored on save.
+    #[serde(default)]
+    pub name: String,
+    /// Relationship to the head, e.g. "Mother" or "Son".
+    #[serde(default)]
+    pub relationship: String,
+}
+
+/// A name from before members were linked to residents that could not be
+/// matched to exactly one resident when the household was migrated.
+#[derive(Debug, Serialize)]
+pub struct UnmatchedHouseholdMember {
+    pub id: i32,
+    pub household_id: i32,
+    pub household_number: i32,
+    pub name: String,
+    pub is_head: bool,
+    /// `not_found` or `ambiguous`.
+    pub reason: String,
+}
+
+impl Validate for Household {
+    fn validate(&self) -> Result<(), BmsError> {
+        let mut ids: Vec<i32> = self.residents.iter().map(|m| m.resident_id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+
+        Validator::new()
+            .check(self.household_number > 0, "household_number", "Household number must be a positive number")
+            .required("type_", &self.type_)
+            .check(self.head_resident_id.is_some(), "head_resident_id", "Choose the head of the household")
+            .check(ids.len() == self.residents.len(), "residents", "A resident is listed more than once")
+            .zone("zone", &self.zone)
+            .date("date", &self.date)
+            .one_of("status", &self.status, HOUSEHOLD_STATUSES)
+            .finish()
+    }
+}
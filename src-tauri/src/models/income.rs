@@ -1,11 +1,146 @@
+use crate::error::BmsError;
+use crate::models::cashbook::Fund;
+use crate::models::money::Money;
+use crate::validation::{Validate, Validator};
+
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub struct Income {
     pub id: Option<i32>,
     pub type_: String,
-    pub amount: f64,
+    pub amount: Money,
+    /// Give 0 when recording a new income to take the next receipt from the
+    /// collector's OR booklet.
+    #[serde(default)]
     pub or_number: i32,
     pub received_from: String,
     pub received_by: String,
     pub category: String,
     pub date: String, // Stored as ISO 8601 string (e.g., "2025-07-21")
-}
\ No newline at end of file
+    #[serde(default)]
+    pub fund: Fund,
+    /// Set when the receipt is voided; ignored on save.
+    #[serde(default)]
+    pub voided_at: Option<String>,
+    #[serde(default)]
+    pub void_reason: Option<String>,
+}
+
+/// Category of the income recorded for a certificate fee.
+pub const CERTIFICATE_INCOME_CATEGORY: &str = "Service Revenue";
+
+/// The largest OR booklet that can be registered, so a typo in the series
+/// does not reserve a million receipts.
+pub const MAX_BOOKLET_SIZE: i32 = 5000;
+
+/// A booklet of pre-numbered official receipts.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct OrBooklet {
+    pub id: Option<i32>,
+    pub series_start: i32,
+    pub series_end: i32,
+    /// The user who issues receipts from the booklet. Unassigned booklets
+    /// are used by anyone whose own booklets have run out.
+    pub collector_id: Option<i32>,
+    /// The collector's username; ignored on save.
+    #[serde(default)]
+    pub collector: Option<String>,
+    #[serde(default)]
+    pub registered_at: String,
+    /// Receipts issued or cancelled so far; ignored on save.
+    #[serde(default)]
+    pub used: i32,
+    /// The receipt that will be issued next, None once the booklet is
+    /// used up; ignored on save.
+    #[serde(default)]
+    pub next_number: Option<i32>,
+}
+
+/// An inclusive run of receipt numbers.
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+pub struct ReceiptRange {
+    pub from: i32,
+    pub to: i32,
+}
+
+/// A receipt whose income was voided after it was issued.
+#[derive(Debug, serde::Serialize)]
+pub struct VoidedReceipt {
+    pub or_number: i32,
+    pub income_id: i32,
+    pub amount: Money,
+    pub reason: Option<String>,
+    pub voided_at: String,
+}
+
+/// A blank receipt that was spoiled or lost and will never be issued.
+#[derive(Debug, serde::Serialize)]
+pub struct CancelledReceipt {
+    pub or_number: i32,
+    pub reason: String,
+    pub username: String,
+    pub cancelled_at: String,
+}
+
+/// Accounts for every number in a booklet, for the Report of Collections
+/// and Deposits.
+#[derive(Debug, serde::Serialize)]
+pub struct BookletReport {
+    pub booklet: OrBooklet,
+    /// Receipts issued for incomes, voided ones included.
+    pub issued: i32,
+    pub voided: Vec<VoidedReceipt>,
+    pub cancelled: Vec<CancelledReceipt>,
+    /// Numbers skipped before the last one used. Each has to be found or
+    /// cancelled.
+    pub gaps: Vec<ReceiptRange>,
+    /// Numbers after the last one used.
+    pub unused: Option<ReceiptRange>,
+    /// Numbers shared by more than one income, recorded before receipt
+    /// numbers were checked.
+    pub duplicates: Vec<i32>,
+}
+
+/// Filters for `query_incomes_command`; unset fields do not filter.
+/// Dates are inclusive ISO dates.
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct IncomeFilter {
+    pub type_: Option<String>,
+    pub category: Option<String>,
+    pub date_from: Option<String>,
+    pub date_to: Option<String>,
+}
+
+impl Validate for Income {
+    fn validate(&self) -> Result<(), BmsError> {
+        Validator::new()
+            .required("type_", &self.type_)
+            .non_negative("amount", self.amount)
+            .check(
+                self.or_number > 0 || (self.id.is_none() && self.or_number == 0),
+                "or_number",
+                "OR number must be a positive number",
+            )
+            .required("received_from", &self.received_from)
+            .required("category", &self.category)
+            .date("date", &self.date)
+            .finish()
+    }
+}
+
+impl Validate for OrBooklet {
+    fn validate(&self) -> Result<(), BmsError> {
+        Validator::new()
+            .check(self.series_start > 0, "series_start", "The first receipt number must be a positive number")
+            .check(
+                self.series_end >= self.series_start,
+                "series_end",
+                "The last receipt number cannot be before the first",
+            )
+            .check(
+                i64::from(self.series_end) - i64::from(self.series_start) < i64::from(MAX_BOOKLET_SIZE),
+                "series_end",
+                &format!("A booklet cannot hold more than {} receipts", MAX_BOOKLET_SIZE),
+            )
+            .finish()
+    }
+}
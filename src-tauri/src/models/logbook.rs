@@ -1,4 +1,6 @@
 use serde::{Serialize, Deserialize};
+use crate::error::BmsError;
+use crate::validation::{Validate, Validator};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Logbook {
@@ -14,4 +16,24 @@ pub struct Logbook {
     pub total_hours: Option<f64>,
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
-}
\ No newline at end of file
+}
+
+/// Filters for `query_logbook_entries_command`; unset fields do not filter.
+/// Dates are inclusive ISO dates.
+#[derive(Debug, Default, Deserialize)]
+pub struct LogbookFilter {
+    pub official_name: Option<String>,
+    pub status: Option<String>,
+    pub date_from: Option<String>,
+    pub date_to: Option<String>,
+}
+
+impl Validate for Logbook {
+    fn validate(&self) -> Result<(), BmsError> {
+        Validator::new()
+            .required("official_name", &self.official_name)
+            .date("date", &self.date)
+            .check(self.total_hours.is_none_or(|h| (0.0..=24.0).contains(&h)), "total_hours", "Total hours must be between 0 and 24")
+            .finish()
+    }
+}
@@ -1,6 +1,11 @@
 pub mod blotter;
+pub mod hearing;
 pub mod income;
+pub mod money;
 pub mod expense;
+pub mod budget;
+pub mod report;
+pub mod cashbook;
 pub mod household;
 pub mod event;
 pub mod settings;
@@ -8,4 +13,8 @@ pub mod resident;
 pub mod official;
 pub mod certificate;
 pub mod user;
-pub mod logbook;
\ No newline at end of file
+pub mod logbook;
+pub mod audit;
+pub mod recycle_bin;
+pub mod query;
+pub mod search;
@@ -0,0 +1,264 @@
+use rusqlite::types::{FromSql, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+/// A peso amount, kept as a whole number of centavos so totals never drift.
+/// Stored as an INTEGER column and serialized as a decimal string such as
+/// `"1234.50"`. Plain JSON numbers are accepted too, under the same rule of
+/// at most two decimal places. Amounts read in are capped at `Money::MAX` either way, so totals
+/// of them stay far from overflowing; arithmetic saturates regardless.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    /// The largest amount accepted as input, 999,999,999.99.
+    pub const MAX: Money = Money::from_centavos(99_999_999_999);
+
+    pub const fn from_centavos(centavos: i64) -> Self {
+        Money(centavos)
+    }
+
+    pub const fn centavos(self) -> i64 {
+        self.0
+    }
+
+    fn within_limit(self) -> bool {
+        self.0.unsigned_abs() <= Money::MAX.0.unsigned_abs()
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        write!(f, "{}{}.{:02}", sign, abs / 100, abs % 100)
+    }
+}
+
+/// Reads amounts like `1234.5`, `-20`, or `1,234.50`. More than two decimal
+/// places is an error rather than a silent rounding, as is an amount beyond
+/// `Money::MAX`.
+impl FromStr for Money {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let digits = digits.replace(',', "");
+        let (whole, fraction) = digits.split_once('.').unwrap_or((&digits, ""));
+
+        let all_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+        if (whole.is_empty() && fraction.is_empty()) || !all_digits(whole) || !all_digits(fraction) {
+            return Err(format!("'{}' is not an amount", s));
+        }
+        if fraction.len() > 2 {
+            return Err("Amounts cannot have more than two decimal places".to_string());
+        }
+
+        let too_large = || format!("'{}' is too large an amount", s);
+        let whole: i64 = if whole.is_empty() { 0 } else { whole.parse().map_err(|_| too_large())? };
+        let fraction: i64 = format!("{:0<2}", fraction).parse().unwrap_or_default();
+        let centavos = whole
+            .checked_mul(100)
+            .and_then(|c| c.checked_add(fraction))
+            .map(Money)
+            .filter(|m| m.within_limit())
+            .ok_or_else(too_large)?;
+
+        Ok(if negative { -centavos } else { centavos })
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+struct MoneyVisitor;
+
+impl Visitor<'_> for MoneyVisitor {
+    type Value = Money;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an amount such as \"1234.50\"")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Money, E> {
+        v.parse().map_err(E::custom)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Money, E> {
+        v.checked_mul(100)
+            .map(Money)
+            .filter(|m| m.within_limit())
+            .ok_or_else(|| E::custom("Amount is too large"))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Money, E> {
+        i64::try_from(v)
+            .map_err(|_| E::custom("Amount is too large"))
+            .and_then(|v| self.visit_i64(v))
+    }
+
+    // Display gives the shortest decimal that reads back as `v`, so 12.345
+    // is refused as a string would be rather than rounded.
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Money, E> {
+        v.to_string().parse().map_err(E::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(MoneyVisitor)
+    }
+}
+
+impl ToSql for Money {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.0))
+    }
+}
+
+impl FromSql for Money {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        i64::column_result(value).map(Money)
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        Money(self.0.saturating_add(other.0))
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        self.0 = self.0.saturating_add(other.0);
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        Money(self.0.saturating_sub(other.0))
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, other: Money) {
+        self.0 = self.0.saturating_sub(other.0);
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money(self.0.saturating_neg())
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, Add::add)
+    }
+}
+
+/// An amount that did not convert to whole centavos exactly when amounts
+/// moved from floating point, listed so the treasurer can check it.
+#[derive(Debug, Serialize)]
+pub struct RoundingAdjustment {
+    pub table_name: String,
+    pub row_id: i64,
+    /// The stored value as it was before conversion.
+    pub original: String,
+    pub amount: Money,
+    pub converted_at: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<Money, String> {
+        s.parse()
+    }
+
+    #[test]
+    fn parses_amounts() {
+        assert_eq!(parse("1234.5"), Ok(Money(123_450)));
+        assert_eq!(parse("1,000.50"), Ok(Money(100_050)));
+        assert_eq!(parse(" 20 "), Ok(Money(2_000)));
+        assert_eq!(parse("+.5"), Ok(Money(50)));
+        assert_eq!(parse("7."), Ok(Money(700)));
+        assert_eq!(parse("-20"), Ok(Money(-2_000)));
+        assert_eq!(parse("-0.05"), Ok(Money(-5)));
+        assert_eq!(parse("999,999,999.99"), Ok(Money::MAX));
+        assert_eq!(parse("-999999999.99"), Ok(-Money::MAX));
+    }
+
+    #[test]
+    fn rejects_bad_amounts() {
+        for bad in ["", " ", "-", ".", "1.005", "abc", "1.2.3", "--1", "1e3", "₱100", "１００"] {
+            assert!(parse(bad).is_err(), "{:?} should not parse", bad);
+        }
+        assert!(parse("1000000000").is_err());
+        assert!(parse("-1000000000").is_err());
+        assert!(parse("92233720368547758.07").is_err());
+        assert!(parse("99999999999999999999").is_err());
+    }
+
+    #[test]
+    fn displays_two_decimals() {
+        assert_eq!(Money(123_450).to_string(), "1234.50");
+        assert_eq!(Money(5).to_string(), "0.05");
+        assert_eq!(Money(-5).to_string(), "-0.05");
+        assert_eq!(Money::ZERO.to_string(), "0.00");
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let amount = Money(123_450);
+        let json = serde_json::to_string(&amount).unwrap();
+        assert_eq!(json, "\"1234.50\"");
+        assert_eq!(serde_json::from_str::<Money>(&json).unwrap(), amount);
+
+        assert_eq!(serde_json::from_str::<Money>("12").unwrap(), Money(1_200));
+        assert_eq!(serde_json::from_str::<Money>("12.5").unwrap(), Money(1_250));
+        assert_eq!(serde_json::from_str::<Money>("999999999.99").unwrap(), Money::MAX);
+        assert!(serde_json::from_str::<Money>("12.345").is_err());
+        assert_eq!(serde_json::from_str::<Money>("\"-3.10\"").unwrap(), Money(-310));
+        assert!(serde_json::from_str::<Money>("\"1.005\"").is_err());
+        assert!(serde_json::from_str::<Money>("10000000000").is_err());
+        assert!(serde_json::from_str::<Money>("18446744073709551615").is_err());
+        assert!(serde_json::from_str::<Money>("1e300").is_err());
+        assert!(serde_json::from_str::<Money>("null").is_err());
+    }
+
+    #[test]
+    fn arithmetic_saturates() {
+        let huge = Money(i64::MAX);
+        assert_eq!(huge + Money(1), huge);
+        assert_eq!(Money(i64::MIN) - Money(1), Money(i64::MIN));
+        assert_eq!(-Money(i64::MIN), huge);
+        assert_eq!([huge, huge].into_iter().sum::<Money>(), huge);
+        assert_eq!([Money(150), Money(-50)].into_iter().sum::<Money>(), Money(100));
+    }
+}
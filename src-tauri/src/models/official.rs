@@ -1,4 +1,6 @@
 use serde::{Deserialize, Serialize};
+use crate::error::BmsError;
+use crate::validation::{Validate, Validator};
 
 #[derive(Serialize, Deserialize)]
 pub struct Official {
@@ -12,4 +14,17 @@ pub struct Official {
     pub zone: String,
     pub image: Option<String>,
     pub section: String,
-}
\ No newline at end of file
+}
+
+impl Validate for Official {
+    fn validate(&self) -> Result<(), BmsError> {
+        Validator::new()
+            .required("name", &self.name)
+            .required("role", &self.role)
+            .check(self.age.is_none_or(|age| (18..=120).contains(&age)), "age", "Enter an age between 18 and 120")
+            .optional_date("term_start", &self.term_start)
+            .optional_date("term_end", &self.term_end)
+            .required("section", &self.section)
+            .finish()
+    }
+}
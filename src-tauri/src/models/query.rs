@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Paging and sorting for `query_*` commands. `page` starts at 1.
+#[derive(Debug, Default, Deserialize)]
+pub struct PageRequest {
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+    pub sort_by: Option<String>,
+    pub sort_dir: Option<SortDirection>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub rows: Vec<T>,
+    /// Rows matching the filters across all pages.
+    pub total: i64,
+    pub page: i64,
+    pub limit: i64,
+}
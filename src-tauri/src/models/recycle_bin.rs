@@ -0,0 +1,11 @@
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct DeletedRecord {
+    pub table_name: String,
+    pub row_id: i64,
+    pub deleted_at: String,
+    pub deleted_by: Option<i32>,
+    /// The row's columns as they were when deleted.
+    pub data: serde_json::Value,
+}
@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use crate::error::BmsError;
+use crate::models::budget::AccountCode;
+use crate::models::money::Money;
+use crate::validation::{Validate, Validator};
+
+/// An inclusive range of ISO dates a report covers.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReportPeriod {
+    pub date_from: String,
+    pub date_to: String,
+}
+
+impl Validate for ReportPeriod {
+    fn validate(&self) -> Result<(), BmsError> {
+        Validator::new()
+            .date("date_from", &self.date_from)
+            .date("date_to", &self.date_to)
+            .check(self.date_from <= self.date_to, "date_to", "The end date cannot be before the start date")
+            .finish()
+    }
+}
+
+/// Which side of the books a breakdown is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ledger {
+    Receipts,
+    Expenditures,
+}
+
+/// One line of a statement: an income category, or the account class (or
+/// category, if not charged to an appropriation) of expenditures.
+#[derive(Debug, Serialize)]
+pub struct ReportLine {
+    pub label: String,
+    pub account_code: Option<AccountCode>,
+    pub amount: Money,
+    pub entries: i64,
+}
+
+/// Statement of Receipts and Expenditures. Voided receipts and deleted
+/// entries are left out.
+#[derive(Debug, Serialize)]
+pub struct ReceiptsAndExpenditures {
+    pub period: ReportPeriod,
+    pub receipts: Vec<ReportLine>,
+    pub total_receipts: Money,
+    pub expenditures: Vec<ReportLine>,
+    pub total_expenditures: Money,
+    /// Receipts less expenditures; negative for a deficit.
+    pub excess: Money,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CashFlowMonth {
+    /// `YYYY-MM`.
+    pub month: String,
+    pub receipts: Money,
+    pub expenditures: Money,
+    pub net: Money,
+    /// Net flow from the start of the period through this month.
+    pub cumulative: Money,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategoryShare {
+    pub category: String,
+    pub amount: Money,
+    pub entries: i64,
+    /// Percent of the total, to two decimals.
+    pub share: f64,
+}
+
+/// An amount in a period against the same dates a year before.
+#[derive(Debug, Serialize)]
+pub struct Comparison {
+    pub label: String,
+    pub current: Money,
+    pub previous: Money,
+    pub change: Money,
+    /// None when there was nothing the year before.
+    pub change_percent: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct YearOverYear {
+    pub period: ReportPeriod,
+    pub previous_period: ReportPeriod,
+    pub receipts: Vec<Comparison>,
+    pub total_receipts: Comparison,
+    pub expenditures: Vec<Comparison>,
+    pub total_expenditures: Comparison,
+}
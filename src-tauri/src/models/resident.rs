@@ -1,4 +1,6 @@
 use serde::{Deserialize, Serialize};
+use crate::error::BmsError;
+use crate::validation::{Validate, Validator, CIVIL_STATUSES, GENDERS, RESIDENT_STATUSES};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Resident {
@@ -34,3 +36,33 @@ pub struct Resident {
     pub is_pwd: bool,              
     pub is_senior: bool,
 }
+
+/// Filters for `query_residents_command`; unset fields do not filter.
+/// Birth dates are inclusive ISO dates.
+#[derive(Debug, Default, Deserialize)]
+pub struct ResidentFilter {
+    pub zone: Option<String>,
+    pub status: Option<String>,
+    pub gender: Option<String>,
+    pub civil_status: Option<String>,
+    pub is_registered_voter: Option<bool>,
+    pub is_pwd: Option<bool>,
+    pub is_senior: Option<bool>,
+    pub born_from: Option<String>,
+    pub born_to: Option<String>,
+}
+
+impl Validate for Resident {
+    fn validate(&self) -> Result<(), BmsError> {
+        Validator::new()
+            .required("first_name", &self.first_name)
+            .required("last_name", &self.last_name)
+            .one_of("civil_status", &self.civil_status, CIVIL_STATUSES)
+            .one_of("gender", &self.gender, GENDERS)
+            .mobile("mobile_number", &self.mobile_number)
+            .date("date_of_birth", &self.date_of_birth)
+            .zone("zone", &self.zone)
+            .one_of("status", &self.status, RESIDENT_STATUSES)
+            .finish()
+    }
+}
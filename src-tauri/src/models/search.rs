@@ -0,0 +1,21 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchKind {
+    Resident,
+    Blotter,
+    Certificate,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchHit {
+    pub kind: SearchKind,
+    pub id: i64,
+    pub title: String,
+    /// Matching text with hits wrapped in `<mark>`…`</mark>`. The surrounding
+    /// text is raw record data and must be escaped before rendering as HTML.
+    pub snippet: String,
+    /// bm25 score; lower is a better match.
+    pub rank: f64,
+}
@@ -1,7 +1,13 @@
 
 
 use serde::{Serialize, Deserialize};
+use crate::error::BmsError;
+use crate::models::money::Money;
+use crate::validation::{Validate, Validator};
 
+/// The barangay's details, stored in bms.db. The certificate signing key is
+/// not among them: it lives in its own file beside the database, which
+/// backups must include (see the README).
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Settings {
     pub id: Option<i32>,
@@ -12,4 +18,32 @@ pub struct Settings {
     pub email: String,
     pub logo: Option<String>,
     pub logo_municipality: Option<String>,
-}
\ No newline at end of file
+}
+
+impl Validate for Settings {
+    fn validate(&self) -> Result<(), BmsError> {
+        let email = self.email.trim();
+        Validator::new()
+            .required("barangay", &self.barangay)
+            .required("municipality", &self.municipality)
+            .required("province", &self.province)
+            .check(email.is_empty() || email.split_once('@').is_some_and(|(user, host)| !user.is_empty() && host.contains('.')), "email", "Enter a valid email address")
+            .finish()
+    }
+}
+
+/// Fee charged for a type of certificate. Types without one are free.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificateFee {
+    pub type_: String,
+    pub amount: Money,
+}
+
+impl Validate for CertificateFee {
+    fn validate(&self) -> Result<(), BmsError> {
+        Validator::new()
+            .required("type_", &self.type_)
+            .non_negative("amount", self.amount)
+            .finish()
+    }
+}
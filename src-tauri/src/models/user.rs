@@ -1,8 +1,75 @@
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use crate::error::BmsError;
+use crate::validation::{Validate, Validator};
 
 #[derive(Serialize, Deserialize)]
 pub struct User {
     pub id: u16,
     pub username: String,
     pub password: String,
+    #[serde(default)]
+    pub role: Role,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Admin,
+    Secretary,
+    Treasurer,
+    Kagawad,
+    #[default]
+    Viewer,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Secretary => "secretary",
+            Role::Treasurer => "treasurer",
+            Role::Kagawad => "kagawad",
+            Role::Viewer => "viewer",
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "admin" => Ok(Role::Admin),
+            "secretary" => Ok(Role::Secretary),
+            "treasurer" => Ok(Role::Treasurer),
+            "kagawad" => Ok(Role::Kagawad),
+            "viewer" => Ok(Role::Viewer),
+            other => Err(format!("Unknown role: {}", other)),
+        }
+    }
+}
+
+/// The logged-in user as seen by command guards; never carries the password.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionUser {
+    pub id: i32,
+    pub username: String,
+    pub role: Role,
+}
+
+/// Returned by `login_command`; `token` must accompany every guarded command.
+#[derive(Debug, Serialize)]
+pub struct LoginSession {
+    pub token: String,
+    pub user: SessionUser,
+}
+
+impl Validate for User {
+    fn validate(&self) -> Result<(), BmsError> {
+        Validator::new()
+            .required("username", &self.username)
+            .required("password", &self.password)
+            .finish()
+    }
 }
@@ -0,0 +1,268 @@
+use crate::error::{BmsError, FieldError};
+use crate::models::money::Money;
+
+pub const RESIDENT_STATUSES: &[&str] = &["Active", "Dead", "Missing", "Moved Out"];
+pub const CIVIL_STATUSES: &[&str] = &["Single", "Married", "Widowed", "Separated", "Lived-In", "Cohabitation"];
+pub const GENDERS: &[&str] = &["Male", "Female"];
+pub const HOUSEHOLD_STATUSES: &[&str] = &["Active", "Moved Out", "Others"];
+pub const EVENT_STATUSES: &[&str] = &["Upcoming", "Ongoing", "Finished", "Cancelled"];
+
+/// Implemented by every model a command writes. Insert and update commands
+/// call `validate` before touching the database.
+pub trait Validate {
+    fn validate(&self) -> Result<(), BmsError>;
+}
+
+/// Collects field errors so the user sees every problem with a form at once
+/// rather than one per save attempt.
+#[derive(Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn error(&mut self, field: &str, message: &str) -> &mut Self {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            message: message.to_string(),
+        });
+        self
+    }
+
+    pub fn check(&mut self, ok: bool, field: &str, message: &str) -> &mut Self {
+        if !ok {
+            self.error(field, message);
+        }
+        self
+    }
+
+    pub fn required(&mut self, field: &str, value: &str) -> &mut Self {
+        self.check(!value.trim().is_empty(), field, "This field is required")
+    }
+
+    pub fn date(&mut self, field: &str, value: &str) -> &mut Self {
+        self.check(is_iso_date(value), field, "Enter a valid date (YYYY-MM-DD)")
+    }
+
+    /// Like `date`, but an empty value is accepted.
+    pub fn optional_date(&mut self, field: &str, value: &str) -> &mut Self {
+        self.check(value.trim().is_empty() || is_iso_date(value), field, "Enter a valid date (YYYY-MM-DD)")
+    }
+
+    pub fn datetime(&mut self, field: &str, value: &str) -> &mut Self {
+        self.check(is_iso_datetime(value), field, "Enter a valid date and time (YYYY-MM-DD HH:MM)")
+    }
+
+    /// Accepts an empty value; anything else must be a Philippine mobile number.
+    pub fn mobile(&mut self, field: &str, value: &str) -> &mut Self {
+        self.check(
+            value.trim().is_empty() || is_ph_mobile(value),
+            field,
+            "Enter a mobile number like 09171234567 or +639171234567",
+        )
+    }
+
+    /// Zero up to `Money::MAX`.
+    pub fn non_negative(&mut self, field: &str, value: Money) -> &mut Self {
+        self.check(!value.is_negative(), field, "Amount cannot be negative")
+            .check(value <= Money::MAX, field, "Amount is too large")
+    }
+
+    /// Zones are whatever the barangay calls them ("Purok 11", "Sitio
+    /// Malinis"), so only a blank one is refused. Commands store them trimmed.
+    pub fn zone(&mut self, field: &str, value: &str) -> &mut Self {
+        self.required(field, value)
+    }
+
+    /// `value` must be one of `allowed`, ignoring case.
+    pub fn one_of(&mut self, field: &str, value: &str, allowed: &[&str]) -> &mut Self {
+        let known = allowed.iter().any(|a| a.eq_ignore_ascii_case(value.trim()));
+        self.check(known, field, "Choose one of the listed values")
+    }
+
+    pub fn finish(&mut self) -> Result<(), BmsError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(BmsError::Validation {
+                message: "Some fields need to be corrected".to_string(),
+                fields: std::mem::take(&mut self.errors),
+            })
+        }
+    }
+}
+
+/// `YYYY-MM-DD`, optionally followed by a time (`T08:30` or ` 08:30:00`,
+/// with the fraction and offset date pickers add). The date itself must
+/// exist on the calendar.
+pub fn is_iso_date(value: &str) -> bool {
+    let value = value.trim();
+    let (Some(date), Some(rest)) = (value.get(..10), value.get(10..)) else {
+        return false;
+    };
+    if !(rest.is_empty() || rest.strip_prefix(['T', ' ']).is_some_and(is_picker_time)) {
+        return false;
+    }
+
+    let parts: Vec<&str> = date.split('-').collect();
+    let [year, month, day] = parts.as_slice() else {
+        return false;
+    };
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 || !date.chars().all(|c| c.is_ascii_digit() || c == '-') {
+        return false;
+    }
+    let (Ok(year), Ok(month), Ok(day)) = (year.parse::<u32>(), month.parse::<u32>(), day.parse::<u32>()) else {
+        return false;
+    };
+
+    let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    year >= 1900 && (1..=days_in_month).contains(&day)
+}
+
+/// `YYYY-MM-DD HH:MM`, with `T` allowed in place of the space and optional
+/// seconds.
+pub fn is_iso_datetime(value: &str) -> bool {
+    let value = value.trim();
+    let (Some(date), Some(separator), Some(time)) = (value.get(..10), value.get(10..11), value.get(11..)) else {
+        return false;
+    };
+    is_iso_date(date) && matches!(separator, "T" | " ") && is_clock_time(time)
+}
+
+/// `HH:MM` or `HH:MM:SS`.
+fn is_clock_time(time: &str) -> bool {
+    let parts: Vec<&str> = time.split(':').collect();
+    let (hour, minute, second) = match parts.as_slice() {
+        [h, m] => (*h, *m, "00"),
+        [h, m, s] => (*h, *m, *s),
+        _ => return false,
+    };
+    let in_range = |part: &str, max: u32| part.len() == 2 && matches!(part.parse::<u32>(), Ok(n) if n <= max);
+    in_range(hour, 23) && in_range(minute, 59) && in_range(second, 59)
+}
+
+/// A clock time as date pickers send it: optional fractional seconds, then
+/// optionally `Z` or an offset like `+08:00`.
+fn is_picker_time(time: &str) -> bool {
+    let time = match time.strip_suffix('Z') {
+        Some(time) => time,
+        None => match time.len().checked_sub(6).and_then(|at| time.get(at..).map(|offset| (at, offset))) {
+            Some((at, offset)) if offset.starts_with(['+', '-']) && is_clock_time(&offset[1..]) => &time[..at],
+            _ => time,
+        },
+    };
+    let time = match time.split_once('.') {
+        Some((time, fraction)) if !fraction.is_empty() && fraction.chars().all(|c| c.is_ascii_digit()) => time,
+        Some(_) => return false,
+        None => time,
+    };
+    is_clock_time(time)
+}
+
+/// `09XXXXXXXXX` or `+639XXXXXXXXX`; spaces and dashes are ignored.
+pub fn is_ph_mobile(value: &str) -> bool {
+    let compact: String = value.chars().filter(|c| !matches!(c, ' ' | '-')).collect();
+    let local = if let Some(rest) = compact.strip_prefix("+63") {
+        format!("0{}", rest)
+    } else {
+        compact
+    };
+    local.len() == 11 && local.starts_with("09") && local.chars().all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iso_dates() {
+        assert!(is_iso_date("2024-02-29"));
+        assert!(is_iso_date(" 2024-01-05 "));
+        assert!(is_iso_date("2024-01-05T08:30:00.000Z"));
+        assert!(is_iso_date("2024-01-05 08:30"));
+        assert!(is_iso_date("2024-01-05T08:30:00+08:00"));
+        assert!(!is_iso_date("2024-01-05 junk"));
+        assert!(!is_iso_date("2024-01-05T"));
+        assert!(!is_iso_date("2024-01-05 25:00"));
+        assert!(!is_iso_date("2024-01-05T08:30:00."));
+        assert!(!is_iso_date("2023-02-29"));
+        assert!(!is_iso_date("1900-02-29"));
+        assert!(!is_iso_date("2024-13-01"));
+        assert!(!is_iso_date("2024-04-31"));
+        assert!(!is_iso_date("1899-12-31"));
+        assert!(!is_iso_date("2024-1-05"));
+        assert!(!is_iso_date("2024-+1-05"));
+        assert!(!is_iso_date("2024-01-05x"));
+        assert!(!is_iso_date("2024-01"));
+        assert!(!is_iso_date(""));
+    }
+
+    #[test]
+    fn iso_dates_with_multibyte_characters() {
+        assert!(!is_iso_date("2024-01-0é"));
+        assert!(!is_iso_date("2024-01-0é…"));
+        assert!(!is_iso_date("２０２４-01-05"));
+        assert!(!is_iso_datetime("2024-01-0é 08:30"));
+        assert!(!is_iso_datetime("2024-01-05é08:30"));
+    }
+
+    #[test]
+    fn iso_datetimes() {
+        assert!(is_iso_datetime("2024-01-05 08:30"));
+        assert!(is_iso_datetime("2024-01-05T23:59:59"));
+        assert!(!is_iso_datetime("2024-01-05"));
+        assert!(!is_iso_datetime("2024-01-05 24:00"));
+        assert!(!is_iso_datetime("2024-01-05 08:60"));
+        assert!(!is_iso_datetime("2024-01-05 8:30"));
+        assert!(!is_iso_datetime("2024-01-05_08:30"));
+        assert!(!is_iso_datetime("2024-02-30 08:30"));
+    }
+
+    #[test]
+    fn ph_mobile_numbers() {
+        assert!(is_ph_mobile("09171234567"));
+        assert!(is_ph_mobile("+639171234567"));
+        assert!(is_ph_mobile("0917-123 4567"));
+        assert!(!is_ph_mobile("9171234567"));
+        assert!(!is_ph_mobile("0817123456"));
+        assert!(!is_ph_mobile("+6309171234567"));
+        assert!(!is_ph_mobile("0917123456a"));
+        assert!(!is_ph_mobile("０９１７１２３４５６７"));
+    }
+
+    #[test]
+    fn zones() {
+        let zone = |value: &str| Validator::new().zone("zone", value).finish().is_ok();
+        assert!(zone("3"));
+        assert!(zone("Purok 11"));
+        assert!(zone("Sitio Malinis"));
+        assert!(!zone("  "));
+        assert!(!zone(""));
+    }
+
+    #[test]
+    fn validator_collects_every_error() {
+        let err = Validator::new()
+            .required("name", " ")
+            .date("date", "2024-01-0é…")
+            .zone("zone", "Zone 3")
+            .finish()
+            .unwrap_err();
+        let BmsError::Validation { fields, .. } = err else {
+            panic!("expected a validation error");
+        };
+        let fields: Vec<&str> = fields.iter().map(|f| f.field.as_str()).collect();
+        assert_eq!(fields, ["name", "date"]);
+    }
+}